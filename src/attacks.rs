@@ -0,0 +1,128 @@
+use crate::bitboard::Bitboard;
+use crate::pawns;
+use crate::side::Side;
+
+/// Squares attacked by pawns of a side
+#[inline]
+pub fn pawn_attacks(pawns: u64, side: Side) -> u64 {
+    pawns::attacks(pawns, side)
+}
+
+/// Squares attacked by knights
+#[inline]
+pub fn knight_attacks(knights: u64) -> u64 {
+    let east = knights.east_shift();
+    let west = knights.west_shift();
+    let east_west = east | west;
+    let double_east = east.east_shift();
+    let double_west = west.west_shift();
+    let double_east_west = double_east | double_west;
+
+    east_west.north_shift().north_shift()
+        | east_west.south_shift().south_shift()
+        | double_east_west.north_shift()
+        | double_east_west.south_shift()
+}
+
+/// Squares attacked by kings
+#[inline]
+pub fn king_attacks(kings: u64) -> u64 {
+    let east_west = kings | kings.east_shift() | kings.west_shift();
+    (east_west | east_west.north_shift() | east_west.south_shift()) & !kings
+}
+
+/// Squares attacked by rooks (including the first blocker in each direction)
+#[inline]
+pub fn rook_attacks(rooks: u64, empty: u64) -> u64 {
+    rooks.north_occluded_fill(empty).north_shift()
+        | rooks.south_occluded_fill(empty).south_shift()
+        | rooks.east_occluded_fill(empty).east_shift()
+        | rooks.west_occluded_fill(empty).west_shift()
+}
+
+/// Squares attacked by bishops (including the first blocker in each direction)
+#[inline]
+pub fn bishop_attacks(bishops: u64, empty: u64) -> u64 {
+    bishops.north_east_occluded_fill(empty).north_east_shift()
+        | bishops.north_west_occluded_fill(empty).north_west_shift()
+        | bishops.south_east_occluded_fill(empty).south_east_shift()
+        | bishops.south_west_occluded_fill(empty).south_west_shift()
+}
+
+/// Squares attacked by queens (including the first blocker in each direction)
+#[inline]
+pub fn queen_attacks(queens: u64, empty: u64) -> u64 {
+    rook_attacks(queens, empty) | bishop_attacks(queens, empty)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bitboard::from_diagram;
+    use crate::square::{masks, named};
+
+    #[test]
+    fn knight_attacks_works() {
+        let expected = from_diagram("
+            . . . . . . . .
+            . . . . . . . .
+            . . . X . X . .
+            . . X . . . X .
+            . . . . . . . .
+            . . X . . . X .
+            . . . X . X . .
+            . . . . . . . .
+        ").unwrap();
+        assert_eq!(knight_attacks(named::E4.mask()), expected);
+        assert_eq!(knight_attacks(named::A1.mask()), named::B3.mask() | named::C2.mask());
+        assert_eq!(knight_attacks(named::H8.mask()), named::G6.mask() | named::F7.mask());
+        assert_eq!(knight_attacks(named::G1.mask()), named::E2.mask() | named::F3.mask() | named::H3.mask());
+    }
+
+    #[test]
+    fn king_attacks_works() {
+        assert_eq!(king_attacks(named::A1.mask()), named::A2.mask() | named::B1.mask() | named::B2.mask());
+        assert_eq!(king_attacks(named::E4.mask()).count_ones(), 8);
+        assert_eq!(king_attacks(named::H5.mask()).count_ones(), 5);
+    }
+
+    #[test]
+    fn rook_attacks_works() {
+        let empty = !(named::E6.mask() | named::C4.mask());
+        let expected = from_diagram("
+            . . . . . . . .
+            . . . . . . . .
+            . . . . X . . .
+            . . . . X . . .
+            . . X X . X X X
+            . . . . X . . .
+            . . . . X . . .
+            . . . . X . . .
+        ").unwrap();
+        assert_eq!(rook_attacks(named::E4.mask(), empty), expected);
+        assert_eq!(rook_attacks(named::A1.mask(), masks::ALL), (masks::A_FILE | masks::RANK_1) & !named::A1.mask());
+    }
+
+    #[test]
+    fn bishop_attacks_works() {
+        let empty = !(named::G6.mask() | named::D3.mask());
+        let expected = from_diagram("
+            X . . . . . . .
+            . X . . . . . .
+            . . X . . . X .
+            . . . X . X . .
+            . . . . . . . .
+            . . . X . X . .
+            . . . . . . X .
+            . . . . . . . X
+        ").unwrap();
+        assert_eq!(bishop_attacks(named::E4.mask(), empty), expected);
+    }
+
+    #[test]
+    fn queen_attacks_works() {
+        let empty = masks::ALL;
+        assert_eq!(queen_attacks(named::D4.mask(), empty), rook_attacks(named::D4.mask(), empty) | bishop_attacks(named::D4.mask(), empty));
+        assert_eq!(queen_attacks(named::A1.mask(), empty).count_ones(), 21);
+    }
+}
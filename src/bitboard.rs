@@ -1,5 +1,8 @@
 
 use crate::square::masks::*;
+use crate::errors::BitboardDiagramError;
+use std::fmt;
+use std::str::FromStr;
 
 pub trait Bitboard {
     fn south_shift(self) -> Self;
@@ -29,6 +32,14 @@ pub trait Bitboard {
     fn north_west_occluded_fill(self, empty: Self) -> Self;
     fn south_east_occluded_fill(self, empty: Self) -> Self;
     fn south_west_occluded_fill(self, empty: Self) -> Self;
+
+    fn flip_vertical(self) -> Self;
+    fn mirror_horizontal(self) -> Self;
+    fn flip_diagonal(self) -> Self;
+    fn flip_anti_diagonal(self) -> Self;
+    fn rotate_90_clockwise(self) -> Self;
+    fn rotate_90_anti_clockwise(self) -> Self;
+    fn rotate_180(self) -> Self;
 }
 
 macro_rules! left_fill_masked {
@@ -110,7 +121,7 @@ impl Bitboard for u64 {
         self >> 9 & NOT_H_FILE
     }
     #[inline]
-    fn file_fill(mut self) -> Self {
+    fn file_fill(self) -> Self {
         self.south_fill() | self.north_fill()
     }
     #[inline]
@@ -199,6 +210,174 @@ impl Bitboard for u64 {
         right_fill_occluded_mask!(self, empty, NOT_H_FILE, 9);
         self
     }
+    /// Flip ranks (rank 1 becomes rank 8)
+    #[inline]
+    fn flip_vertical(self) -> Self {
+        self.swap_bytes()
+    }
+    /// Mirror files (a-file becomes h-file)
+    #[inline]
+    fn mirror_horizontal(mut self) -> Self {
+        const K1: u64 = 0x5555555555555555;
+        const K2: u64 = 0x3333333333333333;
+        const K4: u64 = 0x0f0f0f0f0f0f0f0f;
+        self = ((self >> 1) & K1) | ((self & K1) << 1);
+        self = ((self >> 2) & K2) | ((self & K2) << 2);
+        self = ((self >> 4) & K4) | ((self & K4) << 4);
+
+        self
+    }
+    /// Flip about the a1-h8 diagonal (b1 becomes a2)
+    #[inline]
+    fn flip_diagonal(mut self) -> Self {
+        const K1: u64 = 0x5500550055005500;
+        const K2: u64 = 0x3333000033330000;
+        const K4: u64 = 0x0f0f0f0f00000000;
+        let mut t = K4 & (self ^ (self << 28));
+        self ^= t ^ (t >> 28);
+        t = K2 & (self ^ (self << 14));
+        self ^= t ^ (t >> 14);
+        t = K1 & (self ^ (self << 7));
+        self ^= t ^ (t >> 7);
+
+        self
+    }
+    /// Flip about the a8-h1 diagonal (a1 becomes h8)
+    #[inline]
+    fn flip_anti_diagonal(mut self) -> Self {
+        const K1: u64 = 0xaa00aa00aa00aa00;
+        const K2: u64 = 0xcccc0000cccc0000;
+        const K4: u64 = 0xf0f0f0f00f0f0f0f;
+        let mut t = self ^ (self << 36);
+        self ^= K4 & (t ^ (self >> 36));
+        t = K2 & (self ^ (self << 18));
+        self ^= t ^ (t >> 18);
+        t = K1 & (self ^ (self << 9));
+        self ^= t ^ (t >> 9);
+
+        self
+    }
+    /// Rotate the board a quarter turn clockwise (a1 becomes a8)
+    #[inline]
+    fn rotate_90_clockwise(self) -> Self {
+        self.flip_diagonal().flip_vertical()
+    }
+    /// Rotate the board a quarter turn anti clockwise (a1 becomes h1)
+    #[inline]
+    fn rotate_90_anti_clockwise(self) -> Self {
+        self.flip_vertical().flip_diagonal()
+    }
+    /// Rotate the board a half turn (a1 becomes h8)
+    #[inline]
+    fn rotate_180(self) -> Self {
+        self.reverse_bits()
+    }
+}
+
+/// Character used for a set square in a bitboard diagram
+pub const DIAGRAM_SET: char = 'X';
+/// Character used for an unset square in a bitboard diagram
+pub const DIAGRAM_UNSET: char = '.';
+
+/// Wrapper to display and parse a bitboard as an 8x8 diagram
+///
+/// Rank 8 is printed first with the a-file on the left, set squares as `X` and unset squares as `.`
+/// ```text
+/// 8 . . . . . . . X
+/// 7 . . . . . . X .
+/// 6 . . . . . X . .
+/// 5 . . . . X . . .
+/// 4 . . . X . . . .
+/// 3 . . X . . . . .
+/// 2 . X . . . . . .
+/// 1 X . . . . . . .
+///   a b c d e f g h
+/// ```
+/// Parsing accepts the same format with set squares as `X` or `x`, the rank and file labels as well as any whitespace are optional
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct BitboardDiagram(pub u64);
+
+impl From<u64> for BitboardDiagram {
+    fn from(mask: u64) -> Self {
+        BitboardDiagram(mask)
+    }
+}
+
+impl From<BitboardDiagram> for u64 {
+    fn from(diagram: BitboardDiagram) -> Self {
+        let BitboardDiagram(mask) = diagram;
+        mask
+    }
+}
+
+impl fmt::Display for BitboardDiagram {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let BitboardDiagram(mask) = *self;
+        for y in (0..8u8).rev() {
+            write!(f, "{}", y + 1)?;
+            for x in 0..8u8 {
+                let set = mask & (1u64 << (y * 8 + x)) != 0;
+                write!(f, " {}", if set { DIAGRAM_SET } else { DIAGRAM_UNSET })?;
+            }
+            writeln!(f)?;
+        }
+        write!(f, "  a b c d e f g h")
+    }
+}
+
+impl FromStr for BitboardDiagram {
+    type Err = BitboardDiagramError;
+    /// Parse a diagram (rank 8 first) into a bitboard
+    fn from_str(diagram: &str) -> Result<Self, Self::Err> {
+        let mut mask = 0u64;
+        let mut rank_count = 0u8;
+        for line in diagram.lines() {
+            let cells: String = line.chars().filter(|c| !c.is_whitespace()).collect();
+            // Skip blank lines and the file label footer
+            if cells.is_empty() || cells == "abcdefgh" {
+                continue;
+            }
+            if rank_count == 8 {
+                return Err(BitboardDiagramError::RankCount(rank_count + 1));
+            }
+            let y = 7 - rank_count;
+            let mut cell_chars = cells.chars().peekable();
+            // Optional leading rank label, which has to match the rank being read
+            if let Some(label) = cell_chars.peek().and_then(|c| c.to_digit(10)).filter(|_| cells.chars().count() == 9) {
+                if label != u32::from(y) + 1 {
+                    return Err(BitboardDiagramError::RankLabel(label, y + 1));
+                }
+                cell_chars.next();
+            }
+            let mut x = 0u8;
+            for cell in cell_chars {
+                if x == 8 {
+                    return Err(BitboardDiagramError::RankLength(y + 1, cells.chars().count()));
+                }
+                match cell {
+                    'X' | 'x' => mask |= 1u64 << (y * 8 + x),
+                    '.' => {},
+                    _ => return Err(BitboardDiagramError::InvalidCharacter(cell, y + 1)),
+                }
+                x += 1;
+            }
+            if x != 8 {
+                return Err(BitboardDiagramError::RankLength(y + 1, x as usize));
+            }
+            rank_count += 1;
+        }
+
+        if rank_count != 8 {
+            return Err(BitboardDiagramError::RankCount(rank_count));
+        }
+
+        Ok(BitboardDiagram(mask))
+    }
+}
+
+/// Parse a bitboard from a diagram, see [`BitboardDiagram`] for the format
+pub fn from_diagram(diagram: &str) -> Result<u64, BitboardDiagramError> {
+    diagram.parse::<BitboardDiagram>().map(u64::from)
 }
 
 #[cfg(test)]
@@ -389,4 +568,146 @@ mod test {
         assert_eq!(Bitboard::file_fill(0x4404004001041050), 0x5555555555555555);
         assert_eq!(Bitboard::file_fill(0x28200200000), 0xa2a2a2a2a2a2a2a2);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn diagram_display_works() {
+        let expected = "8 . . . . . . . X\n\
+                        7 . . . . . . . .\n\
+                        6 . . . . . . . .\n\
+                        5 . . . . . . . .\n\
+                        4 . . . . X . . .\n\
+                        3 . . . . . . . .\n\
+                        2 . . . . . . . .\n\
+                        1 X X . . . . . .\n  \
+                          a b c d e f g h";
+        assert_eq!(BitboardDiagram(0x8000000010000003).to_string(), expected);
+        assert_eq!(BitboardDiagram(0x0).to_string().matches(DIAGRAM_SET).count(), 0);
+        assert_eq!(BitboardDiagram(0xffffffffffffffff).to_string().matches(DIAGRAM_SET).count(), 64);
+    }
+
+    #[test]
+    fn diagram_parse_works() {
+        let a_file = from_diagram("
+            X . . . . . . .
+            X . . . . . . .
+            X . . . . . . .
+            X . . . . . . .
+            X . . . . . . .
+            X . . . . . . .
+            X . . . . . . .
+            X . . . . . . .
+        ");
+        assert_eq!(a_file.unwrap(), A_FILE);
+        let labelled = from_diagram("
+            8 . . . . . . . x
+            7 . . . . . . . .
+            6 . . . . . . . .
+            5 . . . . . . . .
+            4 . . . . x . . .
+            3 . . . . . . . .
+            2 . . . . . . . .
+            1 x x . . . . . .
+              a b c d e f g h
+        ");
+        assert_eq!(labelled.unwrap(), 0x8000000010000003);
+    }
+
+    #[test]
+    fn diagram_is_symmetric() {
+        for &mask in &[0x0, 0xffffffffffffffff, 0x3040a1024408800, 0x8142242418000000, RANK_8 | A_FILE] {
+            let diagram = BitboardDiagram(mask).to_string();
+            assert_eq!(from_diagram(&diagram).unwrap(), mask, "Diagram did not round trip\n{}", diagram);
+        }
+    }
+
+    #[test]
+    fn diagram_parse_errors() {
+        assert!(matches!(from_diagram("X......."), Err(BitboardDiagramError::RankCount(1))));
+        assert!(matches!(from_diagram(&"........\n".repeat(9)), Err(BitboardDiagramError::RankCount(9))));
+        assert!(matches!(from_diagram(&format!("{}.......\n", "........\n".repeat(7))), Err(BitboardDiagramError::RankLength(1, 7))));
+        assert!(matches!(from_diagram(&format!("o.......\n{}", "........\n".repeat(7))), Err(BitboardDiagramError::InvalidCharacter('o', 8))));
+        assert!(matches!(from_diagram(&format!("1........\n{}", "........\n".repeat(7))), Err(BitboardDiagramError::RankLabel(1, 8))));
+    }
+
+    #[test]
+    fn flip_vertical_works() {
+        assert_eq!(Bitboard::flip_vertical(RANK_1 | A_FILE), RANK_8 | A_FILE);
+        assert_eq!(Bitboard::flip_vertical(0x3040a1024408800), 0x884024100a0403);
+        assert_eq!(Bitboard::flip_vertical(A1_H8_DIAGONAL), A8_H1_DIAGONAL);
+    }
+
+    #[test]
+    fn mirror_horizontal_works() {
+        assert_eq!(Bitboard::mirror_horizontal(RANK_1 | A_FILE), RANK_1 | H_FILE);
+        assert_eq!(Bitboard::mirror_horizontal(B_FILE | E_FILE), G_FILE | D_FILE);
+        assert_eq!(Bitboard::mirror_horizontal(A1_H8_DIAGONAL), A8_H1_DIAGONAL);
+    }
+
+    #[test]
+    fn flip_diagonal_works() {
+        assert_eq!(Bitboard::flip_diagonal(RANK_1), A_FILE);
+        assert_eq!(Bitboard::flip_diagonal(H_FILE), RANK_8);
+        assert_eq!(Bitboard::flip_diagonal(A1_H8_DIAGONAL), A1_H8_DIAGONAL);
+    }
+
+    #[test]
+    fn flip_anti_diagonal_works() {
+        assert_eq!(Bitboard::flip_anti_diagonal(RANK_1), H_FILE);
+        assert_eq!(Bitboard::flip_anti_diagonal(A_FILE), RANK_8);
+        assert_eq!(Bitboard::flip_anti_diagonal(A8_H1_DIAGONAL), A8_H1_DIAGONAL);
+    }
+
+    #[test]
+    fn rotations_work() {
+        let mask = from_diagram("
+            . . . . . . . .
+            . . . . . . . .
+            . . . . . . . .
+            . . . . . . . .
+            . . . . . . . .
+            X . . . . . . .
+            X . . . . . . .
+            X X X . . . . .
+        ").unwrap();
+        let clockwise = from_diagram("
+            X X X . . . . .
+            X . . . . . . .
+            X . . . . . . .
+            . . . . . . . .
+            . . . . . . . .
+            . . . . . . . .
+            . . . . . . . .
+            . . . . . . . .
+        ").unwrap();
+        let anti_clockwise = from_diagram("
+            . . . . . . . .
+            . . . . . . . .
+            . . . . . . . .
+            . . . . . . . .
+            . . . . . . . .
+            . . . . . . . X
+            . . . . . . . X
+            . . . . . X X X
+        ").unwrap();
+        assert_eq!(Bitboard::rotate_90_clockwise(mask), clockwise);
+        assert_eq!(Bitboard::rotate_90_anti_clockwise(mask), anti_clockwise);
+        assert_eq!(Bitboard::rotate_180(mask), Bitboard::rotate_90_clockwise(clockwise));
+        assert_eq!(Bitboard::rotate_180(RANK_1), RANK_8);
+    }
+
+    #[test]
+    fn transforms_match_square_transforms() {
+        use crate::square::Square;
+        for offset in 0..64 {
+            let square = Square(offset);
+            let mask = square.mask();
+            assert_eq!(Bitboard::flip_vertical(mask), square.flip_vertical().mask());
+            assert_eq!(Bitboard::mirror_horizontal(mask), square.mirror_horizontal().mask());
+            assert_eq!(Bitboard::flip_diagonal(mask), square.flip_diagonal().mask());
+            assert_eq!(Bitboard::flip_anti_diagonal(mask), square.flip_anti_diagonal().mask());
+            assert_eq!(Bitboard::rotate_90_clockwise(mask), square.rotate_90_clockwise().mask());
+            assert_eq!(Bitboard::rotate_90_anti_clockwise(mask), square.rotate_90_anti_clockwise().mask());
+            assert_eq!(Bitboard::rotate_180(mask), square.rotate_180().mask());
+        }
+    }
+}
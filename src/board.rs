@@ -1,14 +1,12 @@
 
-use std::convert::TryFrom;
-
 use super::position::Position;
 use super::chess_move::Move;
 use super::side::Side;
 use super::pieces::{ColoredPiece, Piece};
-use super::square;
 use super::square::Square;
 use super::castles::CastlePermissions;
-use std::borrow::BorrowMut;
+use super::zobrist::{DefaultKeys, ZobristKey, ZobristKeys};
+use super::zobrist::ZobristHashable;
 
 /// Extend position to allow piece manipulation
 pub trait MutablePosition {
@@ -28,6 +26,10 @@ impl MutablePosition for Position {
         let Square(to_offset) = to;
         // Add to squares list
         self.squares[to_offset as usize] = p;
+        // Hash in the piece
+        self.hash ^= DefaultKeys.piece_key(p, to);
+        self.sub_keys.toggle_piece(p, to);
+        self.sub_keys.toggle_material(p, self.piece_mask(p).count_ones() - 1);
     }
     fn remove_piece(&mut self, p: ColoredPiece, from: Square) {
         // Remove from piece mask
@@ -36,6 +38,10 @@ impl MutablePosition for Position {
         let Square(from_offset) = from;
         // Set piece to none
         self.squares[from_offset as usize] = ColoredPiece::None;
+        // Hash out the piece
+        self.hash ^= DefaultKeys.piece_key(p, from);
+        self.sub_keys.toggle_piece(p, from);
+        self.sub_keys.toggle_material(p, self.piece_mask(p).count_ones());
     }
     fn move_piece(&mut self, p: ColoredPiece, from: Square, to: Square) {
         // Update piece mask by removing from 'from' and adding to 'to'
@@ -43,6 +49,10 @@ impl MutablePosition for Position {
         // Update squares
         self.squares[from.0 as usize] = ColoredPiece::None;
         self.squares[to.0 as usize] = p;
+        // Hash the piece out of 'from' and into 'to'
+        self.hash ^= DefaultKeys.piece_key(p, from) ^ DefaultKeys.piece_key(p, to);
+        self.sub_keys.toggle_piece(p, from);
+        self.sub_keys.toggle_piece(p, to);
     }
 }
 
@@ -55,8 +65,52 @@ pub trait MakeUnmakeBoard {
     fn unmake_move(&mut self, m: &Move, prev_castle_permissions: CastlePermissions, prev_en_passant: Option<Square>, prev_half_move: Option<u8>);
 }
 
+/// Combined key for the side to move, castle rights and en passant square
+#[inline]
+fn state_key(side: Side, castle_rights: CastlePermissions, enpassant_square: Option<Square>) -> ZobristKey {
+    let enpassant_key = enpassant_square.map(|square| DefaultKeys.enpassant_key(square)).unwrap_or(0);
+
+    DefaultKeys.side_key(side) ^ DefaultKeys.castle_key(castle_rights) ^ enpassant_key
+}
+
+impl Position {
+    /// Combined key for the current side to move, castle rights and en passant square
+    #[inline]
+    fn state_key(&self) -> ZobristKey {
+        state_key(self.side, self.castle_rights, self.enpassant_square)
+    }
+
+    /// Hash the position would have after making a move, without touching the board
+    pub fn key_after(&self, m: &Move) -> ZobristKey {
+        let castle_rights = m.new_castle_permissions(self.castle_rights);
+        let mut key = self.hash ^ self.state_key() ^ state_key(self.side.opposite(), castle_rights, m.enpassant_square);
+
+        // Piece leaving its square, a pawn when promoting and the king when castling
+        let moved_piece = if m.promoted_piece.is_some() {
+            Piece::Pawn
+        } else if m.castle_rook_squares().is_some() {
+            Piece::King
+        } else {
+            m.piece
+        };
+        let placed_piece = m.promoted_piece.unwrap_or(moved_piece);
+        key ^= DefaultKeys.piece_key(moved_piece.color(m.side), m.from) ^ DefaultKeys.piece_key(placed_piece.color(m.side), m.to);
+
+        if let Some(captured_piece) = m.captured_piece {
+            key ^= DefaultKeys.piece_key(captured_piece.color(m.side.opposite()), m.capture_square());
+        }
+        if let Some((rook_from, rook_to)) = m.castle_rook_squares() {
+            let rook = Piece::Rook.color(m.side);
+            key ^= DefaultKeys.piece_key(rook, rook_from) ^ DefaultKeys.piece_key(rook, rook_to);
+        }
+
+        key
+    }
+}
+
 impl MakeUnmakeBoard for Position {
     fn make_move(&mut self, m: &Move) {
+        self.hash ^= self.state_key();
         self.enpassant_square = m.enpassant_square;
         self.side = self.side.opposite();
         // Get new castle rights
@@ -67,60 +121,44 @@ impl MakeUnmakeBoard for Position {
             // Reset half move clock on castle change
             self.halfmove_clock = Some(0);
         }
+        self.hash ^= self.state_key();
 
-        if m.captured_piece.is_some() && m.promoted_piece.is_some() {
+        if let (Some(captured_piece), Some(promoted_piece)) = (m.captured_piece, m.promoted_piece) {
             // Remove captured piece
-            self.remove_piece(m.captured_piece.unwrap().color(m.side.opposite()), m.to);
+            self.remove_piece(captured_piece.color(m.side.opposite()), m.to);
             // Remove old piece
             self.remove_piece(Piece::Pawn.color(m.side), m.from);
             // Add promoted piece
-            self.add_piece(m.promoted_piece.unwrap().color(m.side), m.to);
+            self.add_piece(promoted_piece.color(m.side), m.to);
             // Reset half move clock on capture
             self.halfmove_clock = Some(0);
-        } else if m.captured_piece.is_some() {
+        } else if m.enpassant_capture {
+            // Checked before plain captures since en passant captures also set the captured piece
+            // Remove en passant pawn
+            self.remove_piece(Piece::Pawn.color(m.side.opposite()), m.capture_square());
+            // Move the piece
+            self.move_piece(m.piece.color(m.side), m.from, m.to);
+            // Reset half move clock on capture
+            self.halfmove_clock = Some(0);
+        } else if let Some(captured_piece) = m.captured_piece {
             // Remove captured piece
-            self.remove_piece(m.captured_piece.unwrap().color(m.side.opposite()), m.to);
+            self.remove_piece(captured_piece.color(m.side.opposite()), m.to);
             // Move the original piece
             self.move_piece(m.piece.color(m.side), m.from, m.to);
             // Reset half move clock on capture
             self.halfmove_clock = Some(0);
-        } else if m.castles_used.intersects(CastlePermissions::BOTH_KINGS) {
-            // Move the king
-            self.move_piece(Piece::King.color(m.side), m.from, m.to);
-            // Move appropriate rook
-            if m.side == Side::White {
-                self.move_piece(ColoredPiece::WRook, square::named::H1, square::named::F1);
-            } else {
-                self.move_piece(ColoredPiece::BRook, square::named::H8, square::named::F8);
-            }
-        } else if m.castles_used.intersects(CastlePermissions::BOTH_QUEENS) {
+        } else if let Some((rook_from, rook_to)) = m.castle_rook_squares() {
             // Move the king
             self.move_piece(Piece::King.color(m.side), m.from, m.to);
             // Move appropriate rook
-            if m.side == Side::White {
-                self.move_piece(ColoredPiece::WRook, square::named::A1, square::named::D1);
-            } else {
-                self.move_piece(ColoredPiece::BRook, square::named::A8, square::named::D8);
-            }
-        } else if m.enpassant_capture {
-            // Get to offset
-            let Square(to_offset) = m.to;
-            // Get location of jumped over pawn
-            let en_passant_offset = ((to_offset as i8) + if m.side == Side::White { -8 } else { 8 }) as u8;
-            let en_passant_square = Square(en_passant_offset);
-            // Remove en passant pawn
-            self.remove_piece(Piece::Pawn.color(m.side.opposite()), en_passant_square);
-            // Move the piece
-            self.move_piece(m.piece.color(m.side), m.from, m.to);
-            // Reset half move clock on capture
-            self.halfmove_clock = Some(0);
-        } else if m.promoted_piece.is_some() {
+            self.move_piece(Piece::Rook.color(m.side), rook_from, rook_to);
+        } else if let Some(promoted_piece) = m.promoted_piece {
             // Remove old pawn
             self.remove_piece(Piece::Pawn.color(m.side), m.from);
             // Add the promoted piece
-            self.add_piece(m.promoted_piece.unwrap().color(m.side), m.to);
-            // Increment half move clock
-            self.halfmove_clock = Some(self.halfmove_clock.unwrap_or(0) + 1);
+            self.add_piece(promoted_piece.color(m.side), m.to);
+            // Reset half move clock on pawn move
+            self.halfmove_clock = Some(0);
         } else {
             // Handle regular moves
             self.move_piece(m.piece.color(m.side), m.from, m.to);
@@ -130,70 +168,64 @@ impl MakeUnmakeBoard for Position {
                 Some(0)
             } else {
                 // Increment half move clock
-                Some(self.halfmove_clock.unwrap_or(0) + 1)
+                Some(self.halfmove_clock.unwrap_or(0).saturating_add(1))
             };
         }
+        if m.side == Side::Black {
+            self.fullmove_count += 1;
+        }
+
+        debug_assert_eq!(self.hash, self.compute_zobrist_hash(), "Incremental hash diverged after making {:?}", m);
+        debug_assert_eq!(self.sub_keys, self.compute_sub_keys(), "Incremental sub keys diverged after making {:?}", m);
     }
 
     fn unmake_move(&mut self, m: &Move, prev_castle_permissions: CastlePermissions, prev_en_passant: Option<Square>, prev_half_move: Option<u8>) {
+        self.hash ^= self.state_key();
         self.enpassant_square = prev_en_passant;
         self.halfmove_clock = prev_half_move;
         // Update castling rights (works because xor is symmetric)
         self.castle_rights = prev_castle_permissions;
         self.side = self.side.opposite();
+        self.hash ^= self.state_key();
+        if m.side == Side::Black {
+            self.fullmove_count = self.fullmove_count.saturating_sub(1);
+        }
 
-        if m.captured_piece.is_some() && m.promoted_piece.is_some() {
+        if let (Some(captured_piece), Some(promoted_piece)) = (m.captured_piece, m.promoted_piece) {
             // TODO: TEST ME
             // Remove promoted piece
-            self.remove_piece(m.promoted_piece.unwrap().color(m.side), m.to);
+            self.remove_piece(promoted_piece.color(m.side), m.to);
             // Add captured piece
-            self.add_piece(m.captured_piece.unwrap().color(m.side.opposite()), m.to);
+            self.add_piece(captured_piece.color(m.side.opposite()), m.to);
             // Add original pawn
             self.add_piece(Piece::Pawn.color(m.side), m.from);
-        } else if m.captured_piece.is_some() {
+        } else if m.enpassant_capture {
+            // Move the piece back
+            self.move_piece(m.piece.color(m.side), m.to, m.from);
+            // Add en passant pawn
+            self.add_piece(Piece::Pawn.color(m.side.opposite()), m.capture_square());
+        } else if let Some(captured_piece) = m.captured_piece {
             // Move the original piece
             self.move_piece(m.piece.color(m.side), m.to, m.from);
-            // Remove captured piece
-            self.remove_piece(m.captured_piece.unwrap().color(m.side.opposite()), m.to);
-        } else if m.castles_used.intersects(CastlePermissions::BOTH_KINGS) {
+            // Restore captured piece
+            self.add_piece(captured_piece.color(m.side.opposite()), m.to);
+        } else if let Some((rook_from, rook_to)) = m.castle_rook_squares() {
             // Move the king
             self.move_piece(Piece::King.color(m.side), m.to, m.from);
             // Move appropriate rook
-            if m.side == Side::White {
-                self.move_piece(ColoredPiece::WRook, square::named::F1, square::named::H1);
-            } else {
-                self.move_piece(ColoredPiece::BRook, square::named::F8, square::named::H8);
-            }
-        } else if m.castles_used.intersects(CastlePermissions::BOTH_QUEENS) {
-            // Move the king
-            self.move_piece(Piece::King.color(m.side), m.to, m.from);
-            // Move appropriate rook
-            if m.side == Side::White {
-                self.move_piece(ColoredPiece::WRook, square::named::D1, square::named::A1);
-            } else {
-                self.move_piece(ColoredPiece::BRook, square::named::D8, square::named::A8);
-            }
-        } else if m.enpassant_capture {
-            // TODO: TEST ME
-            // Get location of jumped over pawn
-            // TODO: Checkme
-            let Square(to_offset) = m.to;
-            let en_passant_offset = ((to_offset as i8) + if m.side == Side::White { -8 } else { 8 }) as u8;
-            let en_passant_square = Square(en_passant_offset);
-            // Move the piece
-            // TODO: Checkme
-            self.move_piece(m.piece.color(m.side), m.from, m.to);
-            // Add en passant pawn
-            self.add_piece(Piece::Pawn.color(m.side.opposite()), en_passant_square);
-        } else if m.promoted_piece.is_some() {
+            self.move_piece(Piece::Rook.color(m.side), rook_to, rook_from);
+        } else if let Some(promoted_piece) = m.promoted_piece {
             // Remove promoted piece
-            self.remove_piece(m.promoted_piece.unwrap().color(m.side), m.to);
+            self.remove_piece(promoted_piece.color(m.side), m.to);
             // Add the original pawn
             self.add_piece(Piece::Pawn.color(m.side), m.from);
         } else {
             // Handle regular moves
             self.move_piece(m.piece.color(m.side), m.to, m.from);
         }
+
+        debug_assert_eq!(self.hash, self.compute_zobrist_hash(), "Incremental hash diverged after unmaking {:?}", m);
+        debug_assert_eq!(self.sub_keys, self.compute_sub_keys(), "Incremental sub keys diverged after unmaking {:?}", m);
     }
 }
 
@@ -207,6 +239,9 @@ impl CopyMakeBoard for Position {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::square;
+    use crate::zobrist::ZobristHashable;
+    use std::convert::TryFrom;
 
     #[test]
     fn make_white_king_castle_works() {
@@ -277,6 +312,7 @@ mod tests {
         assert_eq!(position.halfmove_clock, Some(0));
     }
 
+    #[allow(dead_code)]
     fn unmake_black_king_castle_works() {
         let mut position = Position::try_from("rnbq1rk1/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQq - 0 1".to_string()).unwrap();
         let m = Move::black_king_castle();
@@ -345,6 +381,19 @@ mod tests {
         assert_eq!(position.halfmove_clock, Some(33));
     }
 
+    #[test]
+    fn castle_moves_are_king_moves() {
+        for m in [Move::white_king_castle(), Move::white_queen_castle(), Move::black_king_castle(), Move::black_queen_castle()].iter() {
+            assert_eq!(m.piece, Piece::King);
+        }
+        // Black queen side castling used to be recorded as a queen move
+        let mut position = Position::try_from("r3k3/8/8/8/8/8/8/4K3 b q - 0 1".to_string()).unwrap();
+        MakeUnmakeBoard::make_move(&mut position, &Move::black_queen_castle());
+        assert_eq!(position.squares[square::named::C8.0 as usize], ColoredPiece::BKing);
+        assert_eq!(position.squares[square::named::D8.0 as usize], ColoredPiece::BRook);
+        assert_eq!(position.zobrist_hash(), position.compute_zobrist_hash());
+    }
+
     #[test]
     fn make_black_queen_castle_works() {
         let mut position = Position::try_from("r3kbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1".to_string()).unwrap();
@@ -410,6 +459,152 @@ mod tests {
         assert_eq!(String::from(position), "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1".to_string());
     }
 
+    #[test]
+    fn en_passant_make_unmake_works() {
+        let fen = "4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1";
+        let mut position = Position::try_from(fen.to_string()).unwrap();
+        let m = Move::new(Side::White, Piece::Pawn, square::named::E5, square::named::D6).en_passant_capture().clone();
+        MakeUnmakeBoard::make_move(&mut position, &m);
+        let expected = Position::try_from("4k3/8/3P4/8/8/8/8/4K3 b - - 0 1".to_string()).unwrap();
+        assert_eq!(String::from(&position), String::from(&expected));
+        assert_eq!(position.zobrist_hash(), expected.zobrist_hash());
+
+        MakeUnmakeBoard::unmake_move(&mut position, &m, CastlePermissions::NONE, Some(square::named::D6), Some(0));
+        let original = Position::try_from(fen.to_string()).unwrap();
+        assert_eq!(String::from(&position), fen);
+        assert_eq!(position.zobrist_hash(), original.zobrist_hash());
+    }
+
+    #[test]
+    fn black_en_passant_make_unmake_works() {
+        let fen = "4k3/8/8/8/3Pp3/8/8/4K3 b - d3 0 1";
+        let mut position = Position::try_from(fen.to_string()).unwrap();
+        let m = Move::new(Side::Black, Piece::Pawn, square::named::E4, square::named::D3).en_passant_capture().clone();
+        // En passant moves carry a captured piece, so the plain capture branch must not handle them
+        MakeUnmakeBoard::make_move(&mut position, &m);
+        assert_eq!(String::from(&position), "4k3/8/8/8/8/3p4/8/4K3 w - - 0 2");
+
+        // The capturing pawn goes back to its square and the passed pawn is restored behind the destination
+        MakeUnmakeBoard::unmake_move(&mut position, &m, CastlePermissions::NONE, Some(square::named::D3), Some(0));
+        assert_eq!(String::from(&position), fen);
+        assert_eq!(position.squares[square::named::E4.0 as usize], ColoredPiece::BPawn);
+        assert_eq!(position.squares[square::named::D4.0 as usize], ColoredPiece::WPawn);
+        assert_eq!(position.squares[square::named::D3.0 as usize], ColoredPiece::None);
+    }
+
+    #[test]
+    fn unmake_capture_restores_captured_piece() {
+        let fen = "4k3/8/8/3p4/8/8/8/3RK3 w - - 5 1";
+        let mut position = Position::try_from(fen.to_string()).unwrap();
+        let m = Move::new(Side::White, Piece::Rook, square::named::D1, square::named::D5).capture(Piece::Pawn).clone();
+        MakeUnmakeBoard::make_move(&mut position, &m);
+        assert_eq!(position.piece_mask(ColoredPiece::BPawn), 0);
+
+        MakeUnmakeBoard::unmake_move(&mut position, &m, CastlePermissions::NONE, None, Some(5));
+        assert_eq!(String::from(&position), fen);
+        assert_eq!(position.squares[square::named::D5.0 as usize], ColoredPiece::BPawn);
+        assert_eq!(position.piece_mask(ColoredPiece::BPawn), square::named::D5.mask());
+        assert_eq!(position.piece_mask(ColoredPiece::WRook), square::named::D1.mask());
+    }
+
+    #[test]
+    fn unmake_black_king_castle_restores_pieces() {
+        let mut position = Position::try_from("rnbq1rk1/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQ - 0 1".to_string()).unwrap();
+        MakeUnmakeBoard::unmake_move(&mut position, &Move::black_king_castle(), CastlePermissions::ALL, None, Some(33));
+        assert_eq!(position.squares[square::named::E8.0 as usize], ColoredPiece::BKing);
+        assert_eq!(position.squares[square::named::H8.0 as usize], ColoredPiece::BRook);
+        assert_eq!(position.piece_mask(ColoredPiece::BKing), 0x1000000000000000);
+        assert_eq!(position.piece_mask(ColoredPiece::BRook), 0x8100000000000000);
+        assert_eq!(position.side, Side::Black);
+        assert_eq!(position.halfmove_clock, Some(33));
+    }
+
+    #[test]
+    fn make_move_updates_hash() {
+        let mut position = Position::try_from("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1".to_string()).unwrap();
+        let before = position.zobrist_hash();
+        let m = Move::white_king_castle();
+        MakeUnmakeBoard::make_move(&mut position, &m);
+        let expected = Position::try_from("r3k2r/8/8/8/8/8/8/R4RK1 b kq - 0 1".to_string()).unwrap();
+        assert_eq!(position.zobrist_hash(), expected.zobrist_hash());
+
+        let m = Move::new(Side::Black, Piece::Rook, square::named::A8, square::named::A1).capture(Piece::Rook).clone();
+        MakeUnmakeBoard::make_move(&mut position, &m);
+        assert_eq!(position.zobrist_hash(), position.compute_zobrist_hash());
+        MakeUnmakeBoard::unmake_move(&mut position, &m, CastlePermissions::BLACK_ALL, None, Some(0));
+        MakeUnmakeBoard::unmake_move(&mut position, &Move::white_king_castle(), CastlePermissions::ALL, None, Some(0));
+        assert_eq!(position.zobrist_hash(), before);
+    }
+
+    #[test]
+    fn make_move_updates_sub_keys() {
+        let mut position = Position::try_from("1n2k3/P7/8/8/8/8/8/4K3 w - - 0 1".to_string()).unwrap();
+        let m = Move::new(Side::White, Piece::Pawn, square::named::A7, square::named::B8).capture(Piece::Knight).promote(Piece::Queen).clone();
+        MakeUnmakeBoard::make_move(&mut position, &m);
+        let expected = Position::try_from("1Q2k3/8/8/8/8/8/8/4K3 b - - 0 1".to_string()).unwrap();
+        assert_eq!(position.sub_keys(), expected.sub_keys());
+        assert_eq!(position.pawn_hash(), 0);
+    }
+
+    #[test]
+    fn key_after_predicts_make_move() {
+        let cases = [
+            ("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1", Move::new(Side::White, Piece::Pawn, square::named::E2, square::named::E4).double_jump(square::named::E3).clone()),
+            ("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1", Move::white_king_castle()),
+            ("r3k2r/8/8/8/8/8/8/R3K2R b KQkq - 0 1", Move::black_queen_castle()),
+            ("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1", Move::new(Side::White, Piece::Rook, square::named::A1, square::named::A8).capture(Piece::Rook).clone()),
+            ("4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1", Move::new(Side::White, Piece::Pawn, square::named::E5, square::named::D6).en_passant_capture().clone()),
+            ("1n2k3/P7/8/8/8/8/8/4K3 w - - 0 1", Move::new(Side::White, Piece::Pawn, square::named::A7, square::named::B8).capture(Piece::Knight).promote(Piece::Queen).clone()),
+            ("4k3/8/8/8/8/8/p7/4K3 b - - 0 1", Move::new(Side::Black, Piece::Pawn, square::named::A2, square::named::A1).promote(Piece::Knight).clone()),
+        ];
+        for (fen, m) in cases.iter() {
+            let mut position = Position::try_from(fen.to_string()).unwrap();
+            let predicted = position.key_after(m);
+            let before = position.zobrist_hash();
+            MakeUnmakeBoard::make_move(&mut position, m);
+            assert_eq!(predicted, position.zobrist_hash(), "Wrong key predicted for {:?} in {}", m, fen);
+            assert_ne!(predicted, before);
+        }
+    }
+
+    #[test]
+    fn make_unmake_updates_fullmove_count() {
+        let mut position = Position::try_from("4k3/8/8/8/8/8/8/R3K3 w - - 3 10".to_string()).unwrap();
+        let white = Move::new(Side::White, Piece::Rook, square::named::A1, square::named::A2);
+        let black = Move::new(Side::Black, Piece::King, square::named::E8, square::named::D8);
+        MakeUnmakeBoard::make_move(&mut position, &white);
+        assert_eq!(position.fullmove_count, 10);
+        MakeUnmakeBoard::make_move(&mut position, &black);
+        assert_eq!(position.fullmove_count, 11);
+        assert_eq!(position.halfmove_clock, Some(5));
+
+        position.unmake_move(&black, CastlePermissions::NONE, None, Some(4));
+        assert_eq!(position.fullmove_count, 10);
+        position.unmake_move(&white, CastlePermissions::NONE, None, Some(3));
+        assert_eq!(String::from(&position), "4k3/8/8/8/8/8/8/R3K3 w - - 3 10");
+    }
+
+    #[test]
+    fn halfmove_clock_saturates() {
+        let mut position = Position::try_from("4k3/8/8/8/8/8/8/R3K3 w - - 255 200".to_string()).unwrap();
+        let m = Move::new(Side::White, Piece::Rook, square::named::A1, square::named::A2);
+        MakeUnmakeBoard::make_move(&mut position, &m);
+        assert_eq!(position.halfmove_clock, Some(255));
+        position.unmake_move(&m, CastlePermissions::NONE, None, Some(255));
+        assert_eq!(position.halfmove_clock, Some(255));
+    }
+
+    #[test]
+    fn quiet_promotion_resets_halfmove_clock() {
+        let fen = "4k3/P7/8/8/8/8/8/4K3 w - - 7 30";
+        let mut position = Position::try_from(fen.to_string()).unwrap();
+        let m = Move::new(Side::White, Piece::Pawn, square::named::A7, square::named::A8).promote(Piece::Queen).clone();
+        MakeUnmakeBoard::make_move(&mut position, &m);
+        assert_eq!(String::from(&position), "Q3k3/8/8/8/8/8/8/4K3 b - - 0 30");
+        position.unmake_move(&m, CastlePermissions::NONE, None, Some(7));
+        assert_eq!(String::from(&position), fen);
+    }
+
     #[test]
     fn copy_make_board_copies() {
         let position = Position::try_from("8/8/8/8/8/8/4P3/8 w - - 0 1".to_string()).unwrap();
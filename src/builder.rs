@@ -0,0 +1,167 @@
+use super::board::MutablePosition;
+use super::castles::CastlePermissions;
+use super::errors;
+use super::pieces::ColoredPiece;
+use super::position::Position;
+use super::side::Side;
+use super::square::Square;
+
+/// Board editor that places and removes pieces and sets the position state before producing a validated position
+#[derive(Clone)]
+pub struct PositionBuilder {
+    position: Position,
+}
+
+impl PositionBuilder {
+    /// Start from an empty board with white to move and no castles
+    pub fn new() -> Self {
+        PositionBuilder { position: Position::default() }
+    }
+    /// Piece currently on a square
+    #[inline]
+    pub fn piece_at(&self, square: Square) -> ColoredPiece {
+        self.position.squares[square.offset() as usize]
+    }
+    /// Place a piece on a square replacing anything already there (placing none removes)
+    pub fn piece(&mut self, piece: ColoredPiece, square: Square) -> &mut Self {
+        self.remove(square);
+        if piece != ColoredPiece::None {
+            self.position.add_piece(piece, square);
+        }
+        self
+    }
+    /// Remove whatever piece is on a square
+    pub fn remove(&mut self, square: Square) -> &mut Self {
+        let piece = self.piece_at(square);
+        if piece != ColoredPiece::None {
+            self.position.remove_piece(piece, square);
+        }
+        self
+    }
+    /// Remove every piece from the board keeping the rest of the state
+    pub fn clear(&mut self) -> &mut Self {
+        for offset in 0..64 {
+            self.remove(Square(offset));
+        }
+        self
+    }
+    /// Set the side to move
+    pub fn side(&mut self, side: Side) -> &mut Self {
+        self.position.side = side;
+        self
+    }
+    /// Set the castle rights
+    pub fn castle_rights(&mut self, castle_rights: CastlePermissions) -> &mut Self {
+        self.position.castle_rights = castle_rights;
+        self
+    }
+    /// Set the square behind a pawn that just double jumped
+    pub fn enpassant_square(&mut self, enpassant_square: Option<Square>) -> &mut Self {
+        self.position.enpassant_square = enpassant_square;
+        self
+    }
+    /// Set the number of half moves since the last capture or pawn move
+    pub fn halfmove_clock(&mut self, halfmove_clock: u8) -> &mut Self {
+        self.position.halfmove_clock = Some(halfmove_clock);
+        self
+    }
+    /// Set the full move number
+    pub fn fullmove_count(&mut self, fullmove_count: u16) -> &mut Self {
+        self.position.fullmove_count = fullmove_count;
+        self
+    }
+    /// Validate and produce the position
+    pub fn build(&self) -> Result<Position, Vec<errors::PositionError>> {
+        self.position.validate()?;
+        let mut position = self.position;
+        position.refresh_hash();
+
+        Ok(position)
+    }
+}
+
+impl Default for PositionBuilder {
+    fn default() -> Self {
+        PositionBuilder::new()
+    }
+}
+
+/// Edit an existing position
+impl From<Position> for PositionBuilder {
+    fn from(position: Position) -> Self {
+        PositionBuilder { position }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pieces::PieceRepr;
+    use crate::square::named;
+    use std::convert::TryFrom;
+
+    #[test]
+    fn builds_starting_position() {
+        let mut builder = PositionBuilder::new();
+        let back_rank = [ColoredPiece::WRook, ColoredPiece::WKnight, ColoredPiece::WBishop, ColoredPiece::WQueen, ColoredPiece::WKing, ColoredPiece::WBishop, ColoredPiece::WKnight, ColoredPiece::WRook];
+        for (x, &piece) in back_rank.iter().enumerate() {
+            let x = x as u8;
+            builder.piece(piece, Square(x))
+                .piece(ColoredPiece::WPawn, Square(8 + x))
+                .piece(ColoredPiece::BPawn, Square(48 + x))
+                .piece(ColoredPiece::from(piece.to_ascii().to_ascii_lowercase()), Square(56 + x));
+        }
+        let position = builder.castle_rights(CastlePermissions::ALL).build().unwrap();
+        assert_eq!(String::from(position), "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1");
+    }
+
+    #[test]
+    fn sets_position_state() {
+        let position = PositionBuilder::new()
+            .piece(ColoredPiece::WKing, named::G1)
+            .piece(ColoredPiece::BKing, named::G8)
+            .piece(ColoredPiece::BPawn, named::D5)
+            .side(Side::White)
+            .enpassant_square(Some(named::D6))
+            .halfmove_clock(0)
+            .fullmove_count(42)
+            .build()
+            .unwrap();
+        assert_eq!(String::from(position), "6k1/8/8/3p4/8/8/8/6K1 w - d6 0 42");
+    }
+
+    #[test]
+    fn replaces_and_removes_pieces() {
+        let mut builder = PositionBuilder::from(Position::try_from("4k3/8/8/8/8/8/8/R3K3 w Q - 0 1".to_string()).unwrap());
+        builder.piece(ColoredPiece::WQueen, named::A1);
+        assert_eq!(builder.piece_at(named::A1), ColoredPiece::WQueen);
+        builder.remove(named::A1).castle_rights(CastlePermissions::NONE);
+        assert_eq!(builder.piece_at(named::A1), ColoredPiece::None);
+        let position = builder.build().unwrap();
+        assert_eq!(position.piece_mask(ColoredPiece::WQueen), 0);
+        assert_eq!(position.piece_mask(ColoredPiece::WRook), 0);
+        assert_eq!(String::from(position), "4k3/8/8/8/8/8/8/4K3 w - - 0 1");
+    }
+
+    #[test]
+    fn clear_empties_board() {
+        let mut builder = PositionBuilder::from(Position::try_from("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1".to_string()).unwrap());
+        builder.clear();
+        assert_eq!(builder.piece_at(named::E1), ColoredPiece::None);
+        assert!(builder.build().is_err());
+    }
+
+    #[test]
+    fn build_reports_problems() {
+        let problems = PositionBuilder::new()
+            .piece(ColoredPiece::WKing, named::E1)
+            .castle_rights(CastlePermissions::WHITE_KING)
+            .build()
+            .err()
+            .unwrap();
+        assert_eq!(problems, vec![
+            errors::PositionError::MissingKing(Side::Black),
+            errors::PositionError::InvalidCastleRights(CastlePermissions::WHITE_KING),
+        ]);
+    }
+}
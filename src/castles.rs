@@ -30,26 +30,36 @@ impl Default for CastlePermissions {
     }
 }
 
-impl ToString for CastlePermissions {
-    fn to_string(&self) -> String {
-        match self.clone() {
-            CastlePermissions::WHITE_KING => "K".to_string(),
-            CastlePermissions::WHITE_QUEEN => "Q".to_string(),
-            CastlePermissions::WHITE_ALL => "KQ".to_string(),
-            CastlePermissions::BLACK_KING => "k".to_string(),
-            CastlePermissions::BOTH_KINGS => "Kk".to_string(),
-            CastlePermissions::WHITE_QUEEN_BLACK_KING => "Qk".to_string(),
-            CastlePermissions::WHITE_ALL_BLACK_KING => "KQk".to_string(),
-            CastlePermissions::BLACK_QUEEN => "q".to_string(),
-            CastlePermissions::WHITE_KING_BLACK_QUEEN => "Kq".to_string(),
-            CastlePermissions::BOTH_QUEENS => "Qq".to_string(),
-            CastlePermissions::WHITE_ALL_BLACK_QUEEN => "KQq".to_string(),
-            CastlePermissions::BLACK_ALL => "kq".to_string(),
-            CastlePermissions::BLACK_ALL_WHITE_KING => "Kkq".to_string(),
-            CastlePermissions::BLACK_ALL_WHITE_QUEEN => "Qkq".to_string(),
-            CastlePermissions::ALL => "KQkq".to_string(),
-            CastlePermissions::NONE | _ => "-".to_string(),
-        }
+impl CastlePermissions {
+    /// Give each side the other side's rights
+    pub fn color_flipped(self) -> Self {
+        let white = self.bits() & CastlePermissions::WHITE_ALL.bits();
+        let black = self.bits() & CastlePermissions::BLACK_ALL.bits();
+        CastlePermissions::from_bits_truncate(white << 2 | black >> 2)
+    }
+}
+
+impl std::fmt::Display for CastlePermissions {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let castles = match *self {
+            CastlePermissions::WHITE_KING => "K",
+            CastlePermissions::WHITE_QUEEN => "Q",
+            CastlePermissions::WHITE_ALL => "KQ",
+            CastlePermissions::BLACK_KING => "k",
+            CastlePermissions::BOTH_KINGS => "Kk",
+            CastlePermissions::WHITE_QUEEN_BLACK_KING => "Qk",
+            CastlePermissions::WHITE_ALL_BLACK_KING => "KQk",
+            CastlePermissions::BLACK_QUEEN => "q",
+            CastlePermissions::WHITE_KING_BLACK_QUEEN => "Kq",
+            CastlePermissions::BOTH_QUEENS => "Qq",
+            CastlePermissions::WHITE_ALL_BLACK_QUEEN => "KQq",
+            CastlePermissions::BLACK_ALL => "kq",
+            CastlePermissions::BLACK_ALL_WHITE_KING => "Kkq",
+            CastlePermissions::BLACK_ALL_WHITE_QUEEN => "Qkq",
+            CastlePermissions::ALL => "KQkq",
+            _ => "-",
+        };
+        write!(f, "{}", castles)
     }
 }
 
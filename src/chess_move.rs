@@ -98,8 +98,8 @@ impl Move {
         Move {
             side: Side::White,
             piece: Piece::King,
-            from: square::named::E1.into(),
-            to: square::named::C1.into(),
+            from: square::named::E1,
+            to: square::named::C1,
             captured_piece: None,
             promoted_piece: None,
             enpassant_square: None,
@@ -113,8 +113,8 @@ impl Move {
         Move {
             side: Side::Black,
             piece: Piece::King,
-            from: square::named::E8.into(),
-            to: square::named::G8.into(),
+            from: square::named::E8,
+            to: square::named::G8,
             captured_piece: None,
             promoted_piece: None,
             enpassant_square: None,
@@ -127,9 +127,9 @@ impl Move {
     pub fn black_queen_castle() -> Self {
         Move {
             side: Side::Black,
-            piece: Piece::Queen,
-            from: square::named::E8.into(),
-            to: square::named::C8.into(),
+            piece: Piece::King,
+            from: square::named::E8,
+            to: square::named::C8,
             captured_piece: None,
             promoted_piece: None,
             enpassant_square: None,
@@ -138,6 +138,37 @@ impl Move {
         }
     }
 
+    /// Square of the piece being captured, behind the destination for en passant captures
+    #[inline]
+    pub fn capture_square(&self) -> Square {
+        if self.enpassant_capture {
+            let Square(to_offset) = self.to;
+            Square(if self.side == Side::White { to_offset - 8 } else { to_offset + 8 })
+        } else {
+            self.to
+        }
+    }
+
+    /// Where the rook moves from and to if this move castles
+    #[inline]
+    pub fn castle_rook_squares(&self) -> Option<(Square, Square)> {
+        if self.castles_used.intersects(CastlePermissions::BOTH_KINGS) {
+            Some(if self.side == Side::White {
+                (square::named::H1, square::named::F1)
+            } else {
+                (square::named::H8, square::named::F8)
+            })
+        } else if self.castles_used.intersects(CastlePermissions::BOTH_QUEENS) {
+            Some(if self.side == Side::White {
+                (square::named::A1, square::named::D1)
+            } else {
+                (square::named::A8, square::named::D8)
+            })
+        } else {
+            None
+        }
+    }
+
     pub fn new_castle_permissions(&self, current_rights: CastlePermissions) -> CastlePermissions {
         let mut new_rights: CastlePermissions = current_rights;
         if self.piece == Piece::Rook {
@@ -155,6 +186,16 @@ impl Move {
                 new_rights &= CastlePermissions::WHITE_ALL;
             }
         }
+        // Capturing a rook on its home square takes away the opponent's right to castle with it
+        if self.captured_piece.is_some() {
+            match self.to {
+                square::named::H1 if self.side == Side::Black => { new_rights &= CastlePermissions::BLACK_ALL_WHITE_QUEEN; },
+                square::named::A1 if self.side == Side::Black => { new_rights &= CastlePermissions::BLACK_ALL_WHITE_KING; },
+                square::named::H8 if self.side == Side::White => { new_rights &= CastlePermissions::WHITE_ALL_BLACK_QUEEN; },
+                square::named::A8 if self.side == Side::White => { new_rights &= CastlePermissions::WHITE_ALL_BLACK_KING; },
+                _ => { },
+            }
+        }
 
         new_rights
     }
@@ -162,7 +203,7 @@ impl Move {
 
 impl UCIMove for Move {
     fn to_uci(&self) -> String {
-        let promotion = if self.promoted_piece.is_some() { self.promoted_piece.unwrap().to_ascii().to_string() } else { String::from("") };
-        format!("{}{}{}", self.from.to_string(), self.to.to_string(), promotion)
+        let promotion = self.promoted_piece.map(|piece| piece.to_ascii().to_string()).unwrap_or_default();
+        format!("{}{}{}", self.from, self.to, promotion)
     }
 }
\ No newline at end of file
@@ -0,0 +1,155 @@
+use super::attacks;
+use super::pawns;
+use super::pieces::Piece;
+use super::position::Position;
+use super::side::Side;
+use super::square::masks;
+
+/// Which federation's rules decide if a side still has enough material to win
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub enum MaterialRules {
+    /// A side can win if any sequence of legal moves ends in mate, however unlikely
+    Fide,
+    /// A side can't win with a lone king, a single minor piece, or two knights against a bare king
+    Uscf,
+}
+
+impl Position {
+    /// If a side has no way to ever checkmate its opponent with the material left under `rules`
+    pub fn has_insufficient_material(&self, side: Side, rules: MaterialRules) -> bool {
+        let own = self.side_mask(side);
+        let heavy = self.piece_mask(Piece::Pawn.color(side)) | self.piece_mask(Piece::Rook.color(side)) | self.piece_mask(Piece::Queen.color(side));
+        if heavy != 0 {
+            return false;
+        }
+
+        let knights = self.piece_mask(Piece::Knight.color(side));
+        let bishops = self.piece_mask(Piece::Bishop.color(side));
+        let minors = (knights | bishops).count_ones();
+        match rules {
+            MaterialRules::Uscf => {
+                let enemy = self.side_mask(side.opposite());
+                minors <= 1 || (bishops == 0 && knights.count_ones() == 2 && enemy == self.piece_mask(Piece::King.color(side.opposite())))
+            },
+            MaterialRules::Fide if knights != 0 => {
+                // A lone knight can only mate when the enemy has pieces to block its own king in
+                let enemy = self.side_mask(side.opposite());
+                let enemy_blockers = enemy & !(self.piece_mask(Piece::King.color(side.opposite())) | self.piece_mask(Piece::Queen.color(side.opposite())));
+                own.count_ones() <= 2 && enemy_blockers == 0
+            },
+            MaterialRules::Fide if bishops != 0 => {
+                // Bishops all on one color can't mate unless a pawn or knight blocks the king on the other color
+                let all_bishops = self.piece_mask(Piece::Bishop.color(Side::White)) | self.piece_mask(Piece::Bishop.color(Side::Black));
+                let same_color = all_bishops & masks::DARK_SQUARES == 0 || all_bishops & masks::LIGHT_SQUARES == 0;
+                let blockers = self.piece_mask(Piece::Pawn.color(Side::White))
+                    | self.piece_mask(Piece::Pawn.color(Side::Black))
+                    | self.piece_mask(Piece::Knight.color(Side::White))
+                    | self.piece_mask(Piece::Knight.color(Side::Black));
+                same_color && blockers == 0
+            },
+            MaterialRules::Fide => true,
+        }
+    }
+
+    /// If neither side can win with the material left under `rules`
+    #[inline]
+    pub fn is_insufficient_material(&self, rules: MaterialRules) -> bool {
+        self.has_insufficient_material(Side::White, rules) && self.has_insufficient_material(Side::Black, rules)
+    }
+
+    /// If no sequence of legal moves can lead to checkmate, from a lack of material or a permanently locked pawn structure
+    pub fn is_dead_position(&self) -> bool {
+        self.is_insufficient_material(MaterialRules::Fide) || self.is_locked_pawn_position()
+    }
+
+    /// If only kings and fully locked pawns remain, and neither king can reach an enemy pawn it could take
+    fn is_locked_pawn_position(&self) -> bool {
+        let white_pawns = self.piece_mask(Piece::Pawn.color(Side::White));
+        let black_pawns = self.piece_mask(Piece::Pawn.color(Side::Black));
+        let kings = self.piece_mask(Piece::King.color(Side::White)) | self.piece_mask(Piece::King.color(Side::Black));
+        if self.occupied_mask() != white_pawns | black_pawns | kings || !pawns::is_locked(white_pawns, black_pawns) {
+            return false;
+        }
+
+        let unreachable_pawns = |side: Side, own_pawns: u64, enemy_pawns: u64| {
+            // Enemy king control is ignored, which only makes the reachable area larger
+            let passable = !own_pawns & !pawns::attacks(enemy_pawns, side.opposite());
+            let mut reachable = self.piece_mask(Piece::King.color(side));
+            loop {
+                let next = reachable | (attacks::king_attacks(reachable) & passable);
+                if next == reachable {
+                    return reachable & enemy_pawns == 0;
+                }
+                reachable = next;
+            }
+        };
+
+        unreachable_pawns(Side::White, white_pawns, black_pawns) && unreachable_pawns(Side::Black, black_pawns, white_pawns)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    fn insufficient(fen: &str, rules: MaterialRules) -> bool {
+        Position::from_str(fen).unwrap().is_insufficient_material(rules)
+    }
+
+    #[test]
+    fn insufficient_material_works() {
+        for &rules in [MaterialRules::Fide, MaterialRules::Uscf].iter() {
+            // K vs K, K+N vs K, K+B vs K
+            assert!(insufficient("8/8/4k3/8/8/3K4/8/8 w - - 0 1", rules));
+            assert!(insufficient("8/8/4k3/8/8/3KN3/8/8 w - - 0 1", rules));
+            assert!(insufficient("8/8/4kb2/8/8/3K4/8/8 w - - 0 1", rules));
+            // K+B vs K+B with both bishops on dark squares
+            assert!(insufficient("8/8/4k3/2b5/8/3K4/8/2B5 w - - 0 1", rules));
+            // Any pawn or major piece can still mate
+            assert!(!insufficient("8/8/4k3/8/8/3K4/4P3/8 w - - 0 1", rules));
+            assert!(!insufficient("8/8/4k3/8/8/3K4/8/7r w - - 0 1", rules));
+            // Two bishops of different colors
+            assert!(!insufficient("8/8/4k3/8/8/3KBB2/8/8 w - - 0 1", rules));
+        }
+
+        // K+N+N vs K can't be forced but a helpmate exists
+        assert!(!insufficient("8/8/4k3/8/8/3KNN2/8/8 w - - 0 1", MaterialRules::Fide));
+        assert!(insufficient("8/8/4k3/8/8/3KNN2/8/8 w - - 0 1", MaterialRules::Uscf));
+        // K+B vs K+B on opposite colors can end in a helpmate
+        assert!(!insufficient("8/8/4k3/2b5/8/3K4/8/5B2 w - - 0 1", MaterialRules::Fide));
+        assert!(insufficient("8/8/4k3/2b5/8/3K4/8/5B2 w - - 0 1", MaterialRules::Uscf));
+        // K+N vs K+N
+        assert!(!insufficient("8/8/4kn2/8/8/3KN3/8/8 w - - 0 1", MaterialRules::Fide));
+    }
+
+    #[test]
+    fn has_insufficient_material_works() {
+        // A side with a lone knight can't win against a queen but can against a rook
+        let position = Position::from_str("8/8/4k3/8/8/3KN3/8/7q w - - 0 1").unwrap();
+        assert!(position.has_insufficient_material(Side::White, MaterialRules::Fide));
+        assert!(!position.has_insufficient_material(Side::Black, MaterialRules::Fide));
+        let position = Position::from_str("8/8/4k3/8/8/3KN3/8/7r w - - 0 1").unwrap();
+        assert!(!position.has_insufficient_material(Side::White, MaterialRules::Fide));
+        assert!(position.has_insufficient_material(Side::White, MaterialRules::Uscf));
+    }
+
+    #[test]
+    fn dead_position_works() {
+        assert!(Position::from_str("8/8/4k3/8/8/3K4/8/8 w - - 0 1").unwrap().is_dead_position());
+        assert!(!Position::from_str("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1").unwrap().is_dead_position());
+
+        // Kings separated by a wall of locked pawns
+        let locked = "8/2k5/8/p1p1p1p1/P1P1P1P1/8/5K2/8 w - - 0 1";
+        assert!(Position::from_str(locked).unwrap().is_dead_position());
+        // A pawn that can still advance
+        let unlocked = "8/2k5/8/p1p1p3/P1P1P1P1/8/5K2/8 w - - 0 1";
+        assert!(!Position::from_str(unlocked).unwrap().is_dead_position());
+        // A king on the wrong side of the wall can take an undefended pawn
+        let reachable = "8/2k5/3K4/p1p1p1p1/P1P1P1P1/8/8/8 w - - 0 1";
+        assert!(!Position::from_str(reachable).unwrap().is_dead_position());
+        // Extra pieces can still break through
+        let knight = "8/2k5/8/p1p1p1p1/P1P1P1P1/8/5K2/6N1 w - - 0 1";
+        assert!(!Position::from_str(knight).unwrap().is_dead_position());
+    }
+}
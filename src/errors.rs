@@ -1,6 +1,11 @@
+// err-derive expands its impls inside a named const, which newer compilers flag
+#![allow(non_local_definitions)]
 
 use err_derive::Error;
 use super::square;
+use super::square::Square;
+use super::side::Side;
+use super::castles::CastlePermissions;
 
 
 #[derive(Clone, Debug, Error)]
@@ -27,6 +32,18 @@ pub enum SquareParseError {
     SquareRankError(char),
 }
 
+#[derive(Clone, Debug, Error)]
+#[error(display = "invalid square offset {} expecting 0-63", _0)]
+pub struct SquareOffsetError(pub u8);
+
+#[derive(Clone, Debug, Error)]
+#[error(display = "invalid file index {} expecting 0-7", _0)]
+pub struct FileIndexError(pub u8);
+
+#[derive(Clone, Debug, Error)]
+#[error(display = "invalid rank index {} expecting 0-7", _0)]
+pub struct RankIndexError(pub u8);
+
 #[derive(Clone, Debug, Error)]
 #[error(display = "invalid side string (make sure lowercase)")]
 pub struct InvalidSideError;
@@ -41,14 +58,206 @@ pub struct InvalidCastlesError;
 pub struct InvalidFenString(pub String);
 
 
+/// Field of a FEN string
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum FenField {
+    Board,
+    Side,
+    Castles,
+    EnPassant,
+    HalfmoveClock,
+    FullmoveCount,
+}
+
+impl FenField {
+    pub const ALL: [FenField; 6] = [FenField::Board, FenField::Side, FenField::Castles, FenField::EnPassant, FenField::HalfmoveClock, FenField::FullmoveCount];
+}
+
+impl std::fmt::Display for FenField {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FenField::Board => write!(f, "board"),
+            FenField::Side => write!(f, "side to move"),
+            FenField::Castles => write!(f, "castles"),
+            FenField::EnPassant => write!(f, "en passant"),
+            FenField::HalfmoveClock => write!(f, "half move clock"),
+            FenField::FullmoveCount => write!(f, "full move count"),
+        }
+    }
+}
+
+/// What went wrong while parsing a FEN string
+#[derive(Clone, Debug, Error)]
+pub enum FenErrorKind {
+    #[error(display = "missing field")]
+    MissingField,
+    #[error(display = "unexpected whitespace")]
+    UnexpectedWhitespace,
+    #[error(display = "unexpected characters after the last field")]
+    TrailingCharacters,
+    #[error(display = "invalid piece '{}'", _0)]
+    InvalidPiece(char),
+    #[error(display = "consecutive empty square counts")]
+    ConsecutiveDigits,
+    #[error(display = "rank {} has more than 8 squares", _0)]
+    TooManySquares(char),
+    #[error(display = "rank {} has less than 8 squares", _0)]
+    TooFewSquares(char),
+    #[error(display = "more than 8 ranks")]
+    TooManyRanks,
+    #[error(display = "less than 8 ranks")]
+    TooFewRanks,
+    #[error(display = "invalid side expecting 'w' or 'b'")]
+    InvalidSide,
+    #[error(display = "invalid castle character '{}' expecting 1 of KQkq or -", _0)]
+    InvalidCastles(char),
+    #[error(display = "castle '{}' is repeated or out of KQkq order", _0)]
+    CastlesOrder(char),
+    #[error(display = "invalid square")]
+    InvalidSquare(#[error(source)] SquareParseError),
+    #[error(display = "en passant square {} is not on the rank behind a pawn that just moved", _0)]
+    EnPassantRank(String),
+    #[error(display = "invalid number")]
+    InvalidNumber,
+    #[error(display = "number out of range")]
+    NumberOutOfRange,
+}
+
+/// Error parsing a FEN string with the field and (0 based) character position it occurred at
+#[derive(Clone, Debug, Error)]
+#[error(display = "invalid fen {} field at position {}: {}", field, position, kind)]
+pub struct FenParseError {
+    pub field: FenField,
+    pub position: usize,
+    #[error(source)]
+    pub kind: FenErrorKind,
+}
+
+impl FenParseError {
+    pub fn new(field: FenField, position: usize, kind: FenErrorKind) -> Self {
+        FenParseError { field, position, kind }
+    }
+}
+
 #[derive(Clone, Debug, Error)]
-pub enum FenParseError {
-    #[error(display = "could not parse square '{}' in fen", _1)]
-    SquareParseError(#[error(source)] SquareParseError, String),
-    #[error(display = "could not parse fen chunks")]
-    InvalidFenString(#[error(source)] InvalidFenString),
-    #[error(display = "could not parse side from '{}'", _1)]
-    InvalidSideError(#[error(source)] InvalidSideError, String),
-    #[error(display = "could not parse castles from '{}'", _1)]
-    InvalidCastlesError(#[error(source)] InvalidCastlesError, String),
+pub enum BitboardDiagramError {
+    #[error(display = "bitboard diagram has {} ranks, expected 8", _0)]
+    RankCount(u8),
+
+    #[error(display = "rank {} of bitboard diagram has {} squares, expected 8", _0, _1)]
+    RankLength(u8, usize),
+
+    #[error(display = "invalid character '{}' on rank {} of bitboard diagram expecting '.', 'X' or 'x'", _0, _1)]
+    InvalidCharacter(char, u8),
+
+    #[error(display = "rank label {} does not match expected rank {} of bitboard diagram", _0, _1)]
+    RankLabel(u32, u8),
+}
+
+/// Problem that makes a position illegal or inconsistent
+#[derive(Clone, Debug, Eq, PartialEq, Error)]
+pub enum PositionError {
+    #[error(display = "{} has no king", _0)]
+    MissingKing(Side),
+
+    #[error(display = "{} has {} kings", _0, _1)]
+    TooManyKings(Side, u32),
+
+    #[error(display = "pawn on back rank square {}", _0)]
+    PawnOnBackRank(Square),
+
+    #[error(display = "{} is in check but it is not their move", _0)]
+    OpponentInCheck(Side),
+
+    #[error(display = "castle rights {} do not match the king and rook placement", _0)]
+    InvalidCastleRights(CastlePermissions),
+
+    #[error(display = "en passant square {} does not follow a pawn double jump", _0)]
+    InvalidEnPassant(Square),
+
+    #[error(display = "{} has {} pieces", _0, _1)]
+    TooManyPieces(Side, u32),
+
+    #[error(display = "{} has {} pawns", _0, _1)]
+    TooManyPawns(Side, u32),
+
+    #[error(display = "{} has more promoted pieces than missing pawns", _0)]
+    TooManyPromotedPieces(Side),
+
+    #[error(display = "squares and piece masks disagree on {}", _0)]
+    InconsistentSquare(Square),
+}
+
+#[derive(Debug, Error)]
+pub enum TableFileError {
+    #[error(display = "transposition table file could not be read or written")]
+    Io(#[error(source)] std::io::Error),
+
+    #[error(display = "not a transposition table file")]
+    BadMagic,
+
+    #[error(display = "unsupported transposition table file version {}", _0)]
+    UnsupportedVersion(u16),
+
+    #[error(display = "transposition table file was written with a different zobrist key set")]
+    KeySetMismatch,
+
+    #[error(display = "transposition table file has an invalid layout")]
+    InvalidLayout,
+}
+
+#[derive(Clone, Debug, Error)]
+#[error(display = "illegal move {} in position {}", _0, _1)]
+pub struct IllegalMoveError(pub String, pub String);
+
+#[derive(Clone, Debug, Error)]
+#[error(display = "invalid game result '{}' expecting 1-0, 0-1, 1/2-1/2 or *", _0)]
+pub struct InvalidGameResultError(pub String);
+
+#[derive(Clone, Debug, Eq, PartialEq, Error)]
+pub enum SanError {
+    #[error(display = "invalid SAN move '{}'", _0)]
+    Invalid(String),
+
+    #[error(display = "SAN move '{}' is illegal in this position", _0)]
+    Illegal(String),
+
+    #[error(display = "SAN move '{}' matches more than one legal move", _0)]
+    Ambiguous(String),
+}
+
+#[derive(Debug, Error)]
+pub enum PgnErrorKind {
+    #[error(display = "pgn could not be read")]
+    Io(#[error(source)] std::io::Error),
+
+    #[error(display = "malformed tag pair")]
+    InvalidTag,
+
+    #[error(display = "unexpected end of input inside a {}", _0)]
+    UnexpectedEnd(&'static str),
+
+    #[error(display = "unexpected character '{}'", _0)]
+    UnexpectedCharacter(char),
+
+    #[error(display = "invalid move")]
+    InvalidMove(#[error(source)] SanError),
+
+    #[error(display = "invalid FEN tag")]
+    InvalidFen(#[error(source)] FenParseError),
+
+    #[error(display = "variation without a move to replace")]
+    VariationWithoutMove,
+
+    #[error(display = "unbalanced variation parentheses")]
+    UnbalancedVariation,
+}
+
+#[derive(Debug, Error)]
+#[error(display = "invalid pgn at line {} column {}: {}", line, column, kind)]
+pub struct PgnError {
+    pub line: usize,
+    pub column: usize,
+    #[error(source)]
+    pub kind: PgnErrorKind,
 }
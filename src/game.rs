@@ -0,0 +1,433 @@
+use std::fmt;
+use std::str::FromStr;
+use super::board::MakeUnmakeBoard;
+use super::castles::CastlePermissions;
+use super::chess_move::{Move, UCIMove};
+use super::errors;
+use super::history::KeyHistory;
+use super::position::Position;
+use super::side::Side;
+use super::square::Square;
+use super::zobrist::ZobristKey;
+
+/// Outcome recorded for a game
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Hash)]
+pub enum GameResult {
+    WhiteWins,
+    BlackWins,
+    Draw,
+    /// Still in progress, abandoned or otherwise unknown
+    #[default]
+    Undecided,
+}
+
+impl GameResult {
+    /// Result where a side won
+    #[inline]
+    pub fn win_for(side: Side) -> Self {
+        match side {
+            Side::White => GameResult::WhiteWins,
+            Side::Black => GameResult::BlackWins,
+        }
+    }
+}
+
+impl fmt::Display for GameResult {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GameResult::WhiteWins => write!(f, "1-0"),
+            GameResult::BlackWins => write!(f, "0-1"),
+            GameResult::Draw => write!(f, "1/2-1/2"),
+            GameResult::Undecided => write!(f, "*"),
+        }
+    }
+}
+
+impl FromStr for GameResult {
+    type Err = errors::InvalidGameResultError;
+
+    fn from_str(result: &str) -> Result<Self, Self::Err> {
+        match result {
+            "1-0" => Ok(GameResult::WhiteWins),
+            "0-1" => Ok(GameResult::BlackWins),
+            "1/2-1/2" => Ok(GameResult::Draw),
+            "*" => Ok(GameResult::Undecided),
+            _ => Err(errors::InvalidGameResultError(result.to_string())),
+        }
+    }
+}
+
+/// Why a game ended
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Hash)]
+pub enum Termination {
+    #[default]
+    Unterminated,
+    Checkmate,
+    Stalemate,
+    Resignation,
+    TimeForfeit,
+    Agreement,
+    Repetition,
+    FiftyMoves,
+    DeadPosition,
+    Abandoned,
+    Adjudication,
+}
+
+/// Comments, NAGs and alternative lines attached to a move
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Annotation {
+    /// Comment following the move
+    pub comment: Option<String>,
+    /// Numeric annotation glyphs, like 1 for a good move
+    pub nags: Vec<u8>,
+    /// Lines that could have been played instead of the move
+    pub variations: Vec<Variation>,
+}
+
+/// Alternative line of moves starting from the position before the move it replaces
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Variation {
+    /// Comment before the first move
+    pub comment: Option<String>,
+    pub moves: Vec<Move>,
+    /// One annotation per move
+    pub annotations: Vec<Annotation>,
+}
+
+/// State a move overwrites that unmaking it needs back
+#[derive(Copy, Clone, Debug)]
+struct UndoState {
+    castle_rights: CastlePermissions,
+    enpassant_square: Option<Square>,
+    halfmove_clock: Option<u8>,
+}
+
+/// Record of a game from its starting position, with a cursor for stepping through the moves
+#[derive(Clone)]
+pub struct Game {
+    start: Position,
+    /// Position after the moves up to the cursor
+    position: Position,
+    moves: Vec<Move>,
+    /// One annotation per move
+    annotations: Vec<Annotation>,
+    /// Key of the position after each move
+    keys: Vec<ZobristKey>,
+    /// One entry per move before the cursor
+    undo_states: Vec<UndoState>,
+    /// Keys of the positions before the cursor
+    history: KeyHistory,
+    /// Tag pairs in the order they were added
+    tags: Vec<(String, String)>,
+    pub result: GameResult,
+    pub termination: Termination,
+    /// Comment before the first move
+    pub comment: Option<String>,
+}
+
+impl Game {
+    /// New game without moves starting from a position
+    pub fn new(start: Position) -> Self {
+        Game {
+            position: start,
+            start,
+            moves: Vec::new(),
+            annotations: Vec::new(),
+            keys: Vec::new(),
+            undo_states: Vec::new(),
+            history: KeyHistory::new(),
+            tags: Vec::new(),
+            result: GameResult::Undecided,
+            termination: Termination::Unterminated,
+            comment: None,
+        }
+    }
+
+    /// Position the game started from
+    #[inline]
+    pub fn start_position(&self) -> &Position {
+        &self.start
+    }
+    /// Position at the cursor
+    #[inline]
+    pub fn position(&self) -> &Position {
+        &self.position
+    }
+    /// Every move in the game, including any after the cursor
+    #[inline]
+    pub fn moves(&self) -> &[Move] {
+        &self.moves
+    }
+    /// Annotation of each move, including any after the cursor
+    #[inline]
+    pub fn annotations(&self) -> &[Annotation] {
+        &self.annotations
+    }
+    /// Annotation of a move to edit
+    #[inline]
+    pub fn annotation_mut(&mut self, index: usize) -> Option<&mut Annotation> {
+        self.annotations.get_mut(index)
+    }
+    /// Key of the position after each move
+    #[inline]
+    pub fn keys(&self) -> &[ZobristKey] {
+        &self.keys
+    }
+    /// Keys of the positions before the cursor, for repetition checks
+    #[inline]
+    pub fn key_history(&self) -> &KeyHistory {
+        &self.history
+    }
+    /// Number of moves made to reach the cursor
+    #[inline]
+    pub fn ply(&self) -> usize {
+        self.undo_states.len()
+    }
+    /// Number of moves in the game
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.moves.len()
+    }
+    /// If no moves were played
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.moves.is_empty()
+    }
+    /// Move that led to the position at the cursor
+    #[inline]
+    pub fn last_move(&self) -> Option<&Move> {
+        self.moves[..self.ply()].last()
+    }
+
+    /// Play a legal move at the cursor, dropping any moves that followed it
+    pub fn play(&mut self, m: Move) -> Result<(), errors::IllegalMoveError> {
+        if !self.position.is_legal(&m) {
+            return Err(errors::IllegalMoveError(m.to_uci(), String::from(&self.position)));
+        }
+        self.play_unchecked(m);
+
+        Ok(())
+    }
+
+    /// Play a move already known to be legal at the cursor
+    pub(crate) fn play_unchecked(&mut self, m: Move) {
+        let ply = self.ply();
+        self.moves.truncate(ply);
+        self.annotations.truncate(ply);
+        self.keys.truncate(ply);
+        self.moves.push(m);
+        self.annotations.push(Annotation::default());
+        self.redo();
+    }
+
+    /// Step back one move, returning false at the start of the game
+    pub fn undo(&mut self) -> bool {
+        let state = match self.undo_states.pop() {
+            Some(state) => state,
+            None => return false,
+        };
+        let m = &self.moves[self.undo_states.len()];
+        self.position.unmake_move(m, state.castle_rights, state.enpassant_square, state.halfmove_clock);
+        self.history.pop();
+
+        true
+    }
+
+    /// Step forward one move, returning false at the end of the game
+    pub fn redo(&mut self) -> bool {
+        let ply = self.ply();
+        let m = match self.moves.get(ply) {
+            Some(m) => m,
+            None => return false,
+        };
+        self.undo_states.push(UndoState {
+            castle_rights: self.position.castle_rights,
+            enpassant_square: self.position.enpassant_square,
+            halfmove_clock: self.position.halfmove_clock,
+        });
+        self.history.push(self.position.hash);
+        self.position.make_move(m);
+        if self.keys.len() == ply {
+            self.keys.push(self.position.hash);
+        }
+
+        true
+    }
+
+    /// Move the cursor to a ply, returning false if the game is shorter
+    pub fn go_to(&mut self, ply: usize) -> bool {
+        if ply > self.len() {
+            return false;
+        }
+        while self.ply() > ply {
+            self.undo();
+        }
+        while self.ply() < ply {
+            self.redo();
+        }
+
+        true
+    }
+
+    /// Value of a tag
+    pub fn tag(&self, name: &str) -> Option<&str> {
+        self.tags.iter().find(|(tag, _)| tag == name).map(|(_, value)| value.as_str())
+    }
+    /// Every tag pair in order
+    #[inline]
+    pub fn tags(&self) -> &[(String, String)] {
+        &self.tags
+    }
+    /// Set a tag, keeping its place if it already exists
+    pub fn set_tag(&mut self, name: &str, value: &str) -> &mut Self {
+        match self.tags.iter_mut().find(|(tag, _)| tag == name) {
+            Some((_, existing)) => *existing = value.to_string(),
+            None => self.tags.push((name.to_string(), value.to_string())),
+        }
+        self
+    }
+    /// Remove a tag returning its value
+    pub fn remove_tag(&mut self, name: &str) -> Option<String> {
+        let index = self.tags.iter().position(|(tag, _)| tag == name)?;
+
+        Some(self.tags.remove(index).1)
+    }
+
+    /// Result the rules force at the cursor, ignoring claims, resignations and time
+    pub fn outcome(&self) -> Option<(GameResult, Termination)> {
+        let position = &self.position;
+        let has_legal_moves = position.has_legal_moves();
+        if !has_legal_moves && position.in_check(position.side) {
+            Some((GameResult::win_for(position.side.opposite()), Termination::Checkmate))
+        } else if !has_legal_moves {
+            Some((GameResult::Draw, Termination::Stalemate))
+        } else if position.is_dead_position() {
+            Some((GameResult::Draw, Termination::DeadPosition))
+        } else if position.is_fivefold(&self.history) {
+            Some((GameResult::Draw, Termination::Repetition))
+        } else if position.halfmove_clock.unwrap_or(0) >= 150 {
+            Some((GameResult::Draw, Termination::FiftyMoves))
+        } else {
+            None
+        }
+    }
+}
+
+impl Default for Game {
+    fn default() -> Self {
+        Game::new(Position::start())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pieces::Piece;
+    use crate::square::named;
+
+    fn e4_e5() -> Game {
+        let mut game = Game::default();
+        let mut e4 = Move::new(Side::White, Piece::Pawn, named::E2, named::E4);
+        e4.double_jump(named::E3);
+        let mut e5 = Move::new(Side::Black, Piece::Pawn, named::E7, named::E5);
+        e5.double_jump(named::E6);
+        game.play(e4).unwrap();
+        game.play(e5).unwrap();
+
+        game
+    }
+
+    #[test]
+    fn result_strings_round_trip() {
+        for &result in [GameResult::WhiteWins, GameResult::BlackWins, GameResult::Draw, GameResult::Undecided].iter() {
+            assert_eq!(GameResult::from_str(&result.to_string()).unwrap(), result);
+        }
+        assert!(GameResult::from_str("1-1").is_err());
+    }
+
+    #[test]
+    fn play_rejects_illegal_moves() {
+        let mut game = Game::default();
+        assert!(game.play(Move::new(Side::White, Piece::Pawn, named::E2, named::E5)).is_err());
+        assert!(game.play(Move::new(Side::Black, Piece::Pawn, named::E7, named::E6)).is_err());
+        assert!(game.is_empty());
+    }
+
+    #[test]
+    fn navigation_works() {
+        let mut game = e4_e5();
+        let after_e5 = String::from(game.position());
+        assert_eq!(after_e5, "rnbqkbnr/pppp1ppp/8/4p3/4P3/8/PPPP1PPP/RNBQKBNR w KQkq e6 0 2");
+        assert_eq!(game.keys().len(), 2);
+        assert_eq!(game.keys()[1], game.position().hash);
+
+        assert!(game.undo());
+        assert!(game.undo());
+        assert!(!game.undo());
+        assert_eq!(String::from(game.position()), String::from(game.start_position()));
+        assert_eq!(game.len(), 2);
+
+        assert!(game.go_to(2));
+        assert_eq!(String::from(game.position()), after_e5);
+        assert!(!game.redo());
+        assert!(!game.go_to(3));
+
+        // Playing from an earlier ply replaces the rest of the game
+        assert!(game.go_to(1));
+        game.play(Move::new(Side::Black, Piece::Knight, named::G8, named::F6)).unwrap();
+        assert_eq!(game.len(), 2);
+        assert_eq!(game.last_move().map(|m| m.to), Some(named::F6));
+        assert_eq!(game.keys()[1], game.position().hash);
+    }
+
+    #[test]
+    fn tags_keep_their_order() {
+        let mut game = Game::default();
+        game.set_tag("Event", "?").set_tag("Site", "?").set_tag("Event", "Casual");
+        assert_eq!(game.tag("Event"), Some("Casual"));
+        assert_eq!(game.tags()[0].0, "Event");
+        assert_eq!(game.remove_tag("Site"), Some("?".to_string()));
+        assert_eq!(game.tags().len(), 1);
+    }
+
+    #[test]
+    fn outcome_detects_fivefold_repetition() {
+        let mut game = Game::default();
+        let shuffle = [(named::G1, named::F3), (named::G8, named::F6), (named::F3, named::G1), (named::F6, named::G8)];
+        for _ in 0..4 {
+            for &(from, to) in shuffle.iter() {
+                let side = game.position().side;
+                game.play(Move::new(side, Piece::Knight, from, to)).unwrap();
+            }
+        }
+        assert_eq!(game.key_history().len(), 16);
+        assert!(game.position().is_threefold(game.key_history()));
+        assert_eq!(game.outcome(), Some((GameResult::Draw, Termination::Repetition)));
+
+        assert!(game.undo());
+        assert_eq!(game.key_history().len(), 15);
+        assert_eq!(game.outcome(), None);
+        assert!(game.go_to(0));
+        assert!(game.key_history().is_empty());
+    }
+
+    #[test]
+    fn outcome_works() {
+        let mut game = Game::default();
+        assert_eq!(game.outcome(), None);
+        let moves = [
+            Move::new(Side::White, Piece::Pawn, named::F2, named::F3),
+            Move::new(Side::Black, Piece::Pawn, named::E7, named::E5).double_jump(named::E6).clone(),
+            Move::new(Side::White, Piece::Pawn, named::G2, named::G4).double_jump(named::G3).clone(),
+            Move::new(Side::Black, Piece::Queen, named::D8, named::H4),
+        ];
+        for m in moves.iter() {
+            game.play(m.clone()).unwrap();
+        }
+        assert_eq!(game.outcome(), Some((GameResult::BlackWins, Termination::Checkmate)));
+
+        let game = Game::new(Position::from_str("8/8/4k3/8/8/3KB3/8/8 w - - 0 1").unwrap());
+        assert_eq!(game.outcome(), Some((GameResult::Draw, Termination::DeadPosition)));
+    }
+}
@@ -0,0 +1,187 @@
+use super::position::Position;
+use super::zobrist::ZobristKey;
+
+/// Keys of the positions played before the current one, oldest first
+///
+/// Push the key of a position before making a move from it and pop it after unmaking the move.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct KeyHistory {
+    keys: Vec<ZobristKey>,
+}
+
+impl KeyHistory {
+    /// Empty history, for a position set up without any earlier moves
+    #[inline]
+    pub fn new() -> Self {
+        KeyHistory { keys: Vec::new() }
+    }
+    /// Empty history with room for `capacity` keys
+    #[inline]
+    pub fn with_capacity(capacity: usize) -> Self {
+        KeyHistory { keys: Vec::with_capacity(capacity) }
+    }
+    /// Record the key of a position a move is about to be made from
+    #[inline]
+    pub fn push(&mut self, key: ZobristKey) {
+        self.keys.push(key);
+    }
+    /// Drop the most recent key after unmaking a move
+    #[inline]
+    pub fn pop(&mut self) -> Option<ZobristKey> {
+        self.keys.pop()
+    }
+    /// Every key, oldest first
+    #[inline]
+    pub fn keys(&self) -> &[ZobristKey] {
+        &self.keys
+    }
+    /// Number of keys
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.keys.len()
+    }
+    /// If no keys were pushed
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+    /// Drop every key
+    #[inline]
+    pub fn clear(&mut self) {
+        self.keys.clear();
+    }
+}
+
+impl Position {
+    /// Keys of earlier positions with the same side to move since the last irreversible move, nearest first
+    fn repetition_candidates<'a>(&self, history: &'a KeyHistory) -> impl Iterator<Item = ZobristKey> + 'a {
+        let reversible_plies = (self.halfmove_clock.unwrap_or(0) as usize).min(history.len());
+        history.keys.iter().rev().take(reversible_plies).skip(1).step_by(2).copied()
+    }
+    /// How many times the current position occurred, counting itself
+    fn occurrences(&self, history: &KeyHistory) -> usize {
+        1 + self.repetition_candidates(history).filter(|&key| key == self.hash).count()
+    }
+    /// If the position should be scored as a repetition draw `ply` plies into a search, given the keys that led to it
+    ///
+    /// Repeating a position inside the searched line is enough, positions from before the root have to repeat twice.
+    pub fn is_repetition(&self, history: &KeyHistory, ply: usize) -> bool {
+        let mut earlier_repetitions = 0;
+        for (distance, key) in (2..).step_by(2).zip(self.repetition_candidates(history)) {
+            if key == self.hash {
+                if distance < ply {
+                    return true;
+                }
+                earlier_repetitions += 1;
+                if earlier_repetitions >= 2 {
+                    return true;
+                }
+            }
+        }
+
+        false
+    }
+    /// If the position occurred three times, allowing a draw claim
+    #[inline]
+    pub fn is_threefold(&self, history: &KeyHistory) -> bool {
+        self.occurrences(history) >= 3
+    }
+    /// If the position occurred five times, drawing the game automatically
+    #[inline]
+    pub fn is_fivefold(&self, history: &KeyHistory) -> bool {
+        self.occurrences(history) >= 5
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::MakeUnmakeBoard;
+    use crate::castles::CastlePermissions;
+    use crate::chess_move::Move;
+    use crate::pieces::Piece;
+    use crate::side::Side;
+    use crate::square::named;
+    use crate::zobrist::ZobristHashable;
+    use std::str::FromStr;
+
+    const KNIGHT_SHUFFLE: [(Side, crate::square::Square, crate::square::Square); 4] = [
+        (Side::White, named::G1, named::F3),
+        (Side::Black, named::G8, named::F6),
+        (Side::White, named::F3, named::G1),
+        (Side::Black, named::F6, named::G8),
+    ];
+
+    /// Play knight moves back and forth returning to the starting squares after every 4 plies
+    fn shuffle_knights(position: &mut Position, history: &mut KeyHistory, cycles: usize) {
+        for _ in 0..cycles {
+            for &(side, from, to) in KNIGHT_SHUFFLE.iter() {
+                history.push(position.zobrist_hash());
+                position.make_move(&Move::new(side, Piece::Knight, from, to));
+            }
+        }
+    }
+
+    #[test]
+    fn repetitions_are_counted() {
+        let mut position = Position::start();
+        let mut history = KeyHistory::new();
+        assert!(!position.is_repetition(&history, 0));
+        shuffle_knights(&mut position, &mut history, 1);
+        assert_eq!(history.len(), 4);
+        // Repeated once inside the searched line, but only twice overall
+        assert!(position.is_repetition(&history, 5));
+        assert!(!position.is_repetition(&history, 4));
+        assert!(!position.is_threefold(&history));
+
+        shuffle_knights(&mut position, &mut history, 1);
+        assert!(position.is_repetition(&history, 0));
+        assert!(position.is_threefold(&history));
+        assert!(!position.is_fivefold(&history));
+
+        shuffle_knights(&mut position, &mut history, 2);
+        assert!(position.is_fivefold(&history));
+    }
+
+    #[test]
+    fn repetitions_stop_at_irreversible_moves() {
+        let mut position = Position::start();
+        let mut history = KeyHistory::new();
+        shuffle_knights(&mut position, &mut history, 2);
+        assert!(position.is_threefold(&history));
+
+        // The halfmove clock limits how far back repetitions are looked for
+        let mut limited = position;
+        limited.halfmove_clock = Some(4);
+        assert!(!limited.is_threefold(&history));
+        assert!(!limited.is_repetition(&history, 0));
+
+        // Positions from before an irreversible move don't count even when their key matches
+        let mut reset = position;
+        let mut reset_history = history.clone();
+        reset.halfmove_clock = Some(0);
+        shuffle_knights(&mut reset, &mut reset_history, 1);
+        assert_eq!(reset.halfmove_clock, Some(4));
+        assert_eq!(reset_history.keys().iter().filter(|&&key| key == reset.zobrist_hash()).count(), 3);
+        assert!(!reset.is_threefold(&reset_history));
+        assert!(!reset.is_repetition(&reset_history, 0));
+        assert!(reset.is_repetition(&reset_history, 5));
+        shuffle_knights(&mut position, &mut history, 1);
+        assert!(position.is_repetition(&history, 0));
+    }
+
+    #[test]
+    fn popping_rewinds_the_history() {
+        let mut position = Position::from_str("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1").unwrap();
+        let mut history = KeyHistory::with_capacity(8);
+        shuffle_knights(&mut position, &mut history, 2);
+        let m = Move::new(Side::Black, Piece::Knight, named::F6, named::G8);
+        position.unmake_move(&m, CastlePermissions::ALL, None, Some(7));
+        assert_eq!(history.pop(), Some(position.zobrist_hash()));
+        assert_eq!(history.len(), 7);
+        assert!(!position.is_threefold(&history));
+
+        history.clear();
+        assert!(history.is_empty());
+    }
+}
@@ -7,7 +7,20 @@ pub mod pieces;
 pub mod square;
 pub mod castles;
 pub mod bitboard;
+pub mod pawns;
+pub mod attacks;
 pub mod position;
+pub mod history;
 pub mod zobrist;
+pub mod polyglot;
 pub mod chess_move;
 pub mod board;
+pub mod movegen;
+pub mod san;
+pub mod draw;
+pub mod game;
+pub mod pgn;
+pub mod transposition;
+pub mod builder;
+pub mod render;
+pub mod svg;
@@ -0,0 +1,376 @@
+use super::attacks;
+use super::board::MakeUnmakeBoard;
+use super::castles::CastlePermissions;
+use super::chess_move::Move;
+use super::pieces::{ColoredPiece, Piece};
+use super::position::Position;
+use super::side::Side;
+use super::square::{mask_to_square_iter, named, Rank, Square};
+use super::transposition::PackedMove;
+
+/// Castle moves with the rights they need, the squares that must be empty and the squares the king crosses
+const CASTLES: [(CastlePermissions, Square, Square, u64, [Square; 2]); 4] = [
+    (CastlePermissions::WHITE_KING, named::E1, named::G1, 0x60, [named::F1, named::G1]),
+    (CastlePermissions::WHITE_QUEEN, named::E1, named::C1, 0x0E, [named::D1, named::C1]),
+    (CastlePermissions::BLACK_KING, named::E8, named::G8, 0x6000000000000000, [named::F8, named::G8]),
+    (CastlePermissions::BLACK_QUEEN, named::E8, named::C8, 0x0E00000000000000, [named::D8, named::C8]),
+];
+
+impl Position {
+    /// Full move for packed squares if it is pseudo legal for the side to move (it may still leave the king in check)
+    pub fn unpack_move(&self, packed: PackedMove) -> Option<Move> {
+        let (from, to) = (packed.from_square(), packed.to_square());
+        let side = self.side;
+        let moving = self.squares[from.offset() as usize];
+        let target = self.squares[to.offset() as usize];
+        if from == to || moving == ColoredPiece::None || moving.uncolor().color(side) != moving {
+            return None;
+        }
+        let captured_piece = match target {
+            ColoredPiece::None => None,
+            // Can't capture your own pieces or the enemy king
+            _ if target.uncolor().color(side) == target || target.uncolor() == Piece::King => return None,
+            _ => Some(target.uncolor()),
+        };
+
+        let piece = moving.uncolor();
+        if piece == Piece::Pawn {
+            return self.unpack_pawn_move(from, to, captured_piece, packed.promotion());
+        }
+        if packed.promotion().is_some() {
+            return None;
+        }
+
+        let empty = self.empty_mask();
+        let reachable = match piece {
+            Piece::Knight => attacks::knight_attacks(from.mask()),
+            Piece::Bishop => attacks::bishop_attacks(from.mask(), empty),
+            Piece::Rook => attacks::rook_attacks(from.mask(), empty),
+            Piece::Queen => attacks::queen_attacks(from.mask(), empty),
+            Piece::King => attacks::king_attacks(from.mask()),
+            Piece::Pawn | Piece::None => 0,
+        };
+        if reachable & to.mask() != 0 {
+            let mut m = Move::new(side, piece, from, to);
+            m.captured_piece = captured_piece;
+            return Some(m);
+        }
+
+        if piece == Piece::King && captured_piece.is_none() {
+            return self.unpack_castle(from, to);
+        }
+
+        None
+    }
+
+    /// If packed squares make a pseudo legal move for the side to move
+    #[inline]
+    pub fn is_pseudo_legal(&self, packed: PackedMove) -> bool {
+        self.unpack_move(packed).is_some()
+    }
+
+    /// If a move can be played by the side to move without leaving its king in check
+    pub fn is_legal(&self, m: &Move) -> bool {
+        self.unpack_move(PackedMove::from(m)).as_ref() == Some(m) && self.keeps_king_safe(m)
+    }
+
+    /// Every legal move for the side to move
+    #[inline]
+    pub fn legal_moves(&self) -> Vec<Move> {
+        self.legal_move_iter().collect()
+    }
+
+    /// If the side to move has any legal move, stopping at the first one found
+    #[inline]
+    pub fn has_legal_moves(&self) -> bool {
+        self.legal_move_iter().next().is_some()
+    }
+
+    /// If the side to move is in check without any legal move
+    #[inline]
+    pub fn is_checkmate(&self) -> bool {
+        self.in_check(self.side) && !self.has_legal_moves()
+    }
+
+    /// If the side to move is not in check but has no legal move
+    #[inline]
+    pub fn is_stalemate(&self) -> bool {
+        !self.in_check(self.side) && !self.has_legal_moves()
+    }
+
+    /// If a pseudo legal move doesn't leave the mover's king in check
+    fn keeps_king_safe(&self, m: &Move) -> bool {
+        let mut after = *self;
+        after.make_move(m);
+
+        !after.in_check(m.side)
+    }
+
+    /// Legal moves generated piece by piece from attack masks
+    fn legal_move_iter(&self) -> impl Iterator<Item = Move> + '_ {
+        let own = self.side_mask(self.side);
+        mask_to_square_iter(own)
+            .flat_map(move |from| mask_to_square_iter(self.destinations(from, own)).map(move |to| (from, to)))
+            .flat_map(move |(from, to)| {
+                let promotions: &[Option<Piece>] = if self.squares[from.offset() as usize].uncolor() == Piece::Pawn && to.relative_rank(self.side) == Rank::Eighth {
+                    &[Some(Piece::Knight), Some(Piece::Bishop), Some(Piece::Rook), Some(Piece::Queen)]
+                } else {
+                    &[None]
+                };
+                promotions.iter().filter_map(move |&promotion| self.unpack_move(PackedMove::new(from, to, promotion)))
+            })
+            .filter(move |m| self.keeps_king_safe(m))
+    }
+
+    /// Squares a piece of the side to move might reach, a superset of its pseudo legal destinations
+    fn destinations(&self, from: Square, own: u64) -> u64 {
+        let empty = self.empty_mask();
+        let reachable = match self.squares[from.offset() as usize].uncolor() {
+            Piece::Pawn => {
+                let forward = if self.side == Side::White { from.mask() << 8 } else { from.mask() >> 8 };
+                let double = if self.side == Side::White { (forward & empty) << 8 } else { (forward & empty) >> 8 };
+                forward | double | attacks::pawn_attacks(from.mask(), self.side)
+            },
+            Piece::Knight => attacks::knight_attacks(from.mask()),
+            Piece::Bishop => attacks::bishop_attacks(from.mask(), empty),
+            Piece::Rook => attacks::rook_attacks(from.mask(), empty),
+            Piece::Queen => attacks::queen_attacks(from.mask(), empty),
+            Piece::King => CASTLES.iter().filter(|castle| castle.1 == from).fold(attacks::king_attacks(from.mask()), |mask, castle| mask | castle.2.mask()),
+            Piece::None => 0,
+        };
+
+        reachable & !own
+    }
+
+    fn unpack_pawn_move(&self, from: Square, to: Square, captured_piece: Option<Piece>, promotion: Option<Piece>) -> Option<Move> {
+        let side = self.side;
+        let forward: i8 = if side == Side::White { 1 } else { -1 };
+        // Promote exactly when reaching the last rank
+        if (to.relative_rank(side) == Rank::Eighth) != promotion.is_some() {
+            return None;
+        }
+
+        let mut m = Move::new(side, Piece::Pawn, from, to);
+        m.promoted_piece = promotion;
+        if attacks::pawn_attacks(from.mask(), side) & to.mask() != 0 {
+            match captured_piece {
+                Some(captured_piece) => {
+                    m.capture(captured_piece);
+                },
+                None if self.enpassant_square == Some(to) => {
+                    m.en_passant_capture();
+                },
+                None => return None,
+            }
+            return Some(m);
+        }
+        if captured_piece.is_some() {
+            return None;
+        }
+
+        let single = from.translate(0, forward)?;
+        if self.squares[single.offset() as usize] != ColoredPiece::None {
+            return None;
+        }
+        if to == single {
+            return Some(m);
+        }
+        if from.relative_rank(side) == Rank::Second && Some(to) == single.translate(0, forward) {
+            m.double_jump(single);
+            return Some(m);
+        }
+
+        None
+    }
+
+    fn unpack_castle(&self, from: Square, to: Square) -> Option<Move> {
+        let &(right, _, _, between, crossed) = CASTLES.iter().find(|castle| castle.1 == from && castle.2 == to)?;
+        let rook_square = match right {
+            CastlePermissions::WHITE_KING => named::H1,
+            CastlePermissions::WHITE_QUEEN => named::A1,
+            CastlePermissions::BLACK_KING => named::H8,
+            _ => named::A8,
+        };
+        let enemy = self.side.opposite();
+        let pseudo_legal = self.castle_rights.contains(right)
+            && right.intersects(if self.side == Side::White { CastlePermissions::WHITE_ALL } else { CastlePermissions::BLACK_ALL })
+            && self.squares[rook_square.offset() as usize] == Piece::Rook.color(self.side)
+            && self.occupied_mask() & between == 0
+            && !self.in_check(self.side)
+            && !crossed.iter().any(|&square| self.is_square_attacked(square, enemy));
+        if !pseudo_legal {
+            return None;
+        }
+
+        Some(match right {
+            CastlePermissions::WHITE_KING => Move::white_king_castle(),
+            CastlePermissions::WHITE_QUEEN => Move::white_queen_castle(),
+            CastlePermissions::BLACK_KING => Move::black_king_castle(),
+            _ => Move::black_queen_castle(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    fn pseudo_legal_count(fen: &str) -> usize {
+        let position = Position::from_str(fen).unwrap();
+        let promotions = [None, Some(Piece::Knight), Some(Piece::Bishop), Some(Piece::Rook), Some(Piece::Queen)];
+        let mut count = 0;
+        for from in 0..64 {
+            for to in 0..64 {
+                for &promotion in promotions.iter() {
+                    if position.is_pseudo_legal(PackedMove::new(Square(from), Square(to), promotion)) {
+                        count += 1;
+                    }
+                }
+            }
+        }
+
+        count
+    }
+
+    #[test]
+    fn counts_pseudo_legal_moves() {
+        assert_eq!(pseudo_legal_count("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1"), 20);
+        assert_eq!(pseudo_legal_count("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1"), 20);
+        assert_eq!(pseudo_legal_count("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1"), 48);
+        // Includes b5b6 which exposes the king
+        assert_eq!(pseudo_legal_count("8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1"), 16);
+    }
+
+    #[test]
+    fn unpacks_special_moves() {
+        let position = Position::from_str("r3k2r/1P6/8/3pP3/8/8/8/R3K2R w KQkq d6 0 1").unwrap();
+        let en_passant = position.unpack_move(PackedMove::new(named::E5, named::D6, None)).unwrap();
+        assert!(en_passant.enpassant_capture);
+        assert_eq!(en_passant.captured_piece, Some(Piece::Pawn));
+
+        let promotion = position.unpack_move(PackedMove::new(named::B7, named::A8, Some(Piece::Queen))).unwrap();
+        assert_eq!(promotion.captured_piece, Some(Piece::Rook));
+        assert_eq!(promotion.promoted_piece, Some(Piece::Queen));
+        assert!(!position.is_pseudo_legal(PackedMove::new(named::B7, named::B8, None)));
+        assert!(!position.is_pseudo_legal(PackedMove::new(named::E5, named::E6, Some(Piece::Queen))));
+
+        assert_eq!(position.unpack_move(PackedMove::new(named::E1, named::G1, None)), Some(Move::white_king_castle()));
+        assert_eq!(position.unpack_move(PackedMove::new(named::E1, named::C1, None)), Some(Move::white_queen_castle()));
+    }
+
+    #[test]
+    fn counts_legal_moves() {
+        let count = |fen: &str| Position::from_str(fen).unwrap().legal_moves().len();
+        assert_eq!(count("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1"), 20);
+        assert_eq!(count("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1"), 48);
+        assert_eq!(count("8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1"), 14);
+        assert_eq!(count("r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1"), 6);
+    }
+
+    fn perft(position: &mut Position, depth: usize) -> usize {
+        if depth == 0 {
+            return 1;
+        }
+        let mut nodes = 0;
+        for m in position.legal_moves() {
+            let (castle_rights, enpassant_square, halfmove_clock) = (position.castle_rights, position.enpassant_square, position.halfmove_clock);
+            position.make_move(&m);
+            nodes += perft(position, depth - 1);
+            position.unmake_move(&m, castle_rights, enpassant_square, halfmove_clock);
+        }
+
+        nodes
+    }
+
+    #[test]
+    fn legal_moves_match_every_legal_packed_move() {
+        let promotions = [None, Some(Piece::Knight), Some(Piece::Bishop), Some(Piece::Rook), Some(Piece::Queen)];
+        let fens = [
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "r3k2r/1P6/8/3pP3/8/8/8/R3K2R w KQkq d6 0 1",
+            "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 b kq - 0 1",
+        ];
+        for fen in fens.iter() {
+            let position = Position::from_str(fen).unwrap();
+            let mut brute_force = Vec::new();
+            for from in 0..64 {
+                for to in 0..64 {
+                    for &promotion in promotions.iter() {
+                        if let Some(m) = position.unpack_move(PackedMove::new(Square(from), Square(to), promotion)) {
+                            if position.is_legal(&m) {
+                                brute_force.push(m);
+                            }
+                        }
+                    }
+                }
+            }
+            let mut generated = position.legal_moves();
+            let key = |m: &Move| (m.from.offset(), m.to.offset(), m.promoted_piece.map(|piece| piece as u8));
+            generated.sort_by_key(key);
+            brute_force.sort_by_key(key);
+            assert_eq!(generated, brute_force, "Generated moves differ in {}", fen);
+        }
+    }
+
+    #[test]
+    fn perft_counts_match() {
+        let mut start = Position::from_str("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1").unwrap();
+        assert_eq!(perft(&mut start, 3), 8902);
+        let mut kiwipete = Position::from_str("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1").unwrap();
+        assert_eq!(perft(&mut kiwipete, 2), 2039);
+    }
+
+    #[test]
+    fn captured_rooks_lose_castle_rights() {
+        let mut position = Position::from_str("r3k2r/8/8/8/8/8/1B6/4K3 w kq - 0 1").unwrap();
+        let m = position.parse_san("Bxh8").unwrap();
+        position.make_move(&m);
+        assert_eq!(String::from(&position), "r3k2B/8/8/8/8/8/8/4K3 b q - 0 1");
+
+        // The a8 rook walks round to h8, which must not bring king side castling back
+        for san in ["Rb8", "Kd1", "Rb1+", "Kd2", "Rh1", "Ke2", "Rh8", "Ke1"].iter() {
+            let m = position.parse_san(san).unwrap();
+            position.make_move(&m);
+        }
+        assert_eq!(position.castle_rights, CastlePermissions::NONE);
+        assert!(position.parse_san("O-O").is_err());
+        assert!(!position.is_legal(&Move::black_king_castle()));
+        assert!(!position.legal_moves().contains(&Move::black_king_castle()));
+
+        // Rooks captured on a1 and h1 too
+        let position = Position::from_str("4k3/8/8/8/8/8/6b1/R3K2R b KQ - 0 1").unwrap();
+        let m = position.parse_san("Bxh1").unwrap();
+        assert_eq!(m.new_castle_permissions(position.castle_rights), CastlePermissions::WHITE_QUEEN);
+        let position = Position::from_str("4k3/8/8/8/8/8/1b6/R3K2R b KQ - 0 1").unwrap();
+        let m = position.parse_san("Bxa1").unwrap();
+        assert_eq!(m.new_castle_permissions(position.castle_rights), CastlePermissions::WHITE_KING);
+    }
+
+    #[test]
+    fn detects_mate_and_stalemate() {
+        let mate = Position::from_str("rnb1kbnr/pppp1ppp/8/4p3/6Pq/5P2/PPPPP2P/RNBQKBNR w KQkq - 1 3").unwrap();
+        assert!(mate.is_checkmate());
+        assert!(!mate.is_stalemate());
+        let stalemate = Position::from_str("7k/5Q2/6K1/8/8/8/8/8 b - - 0 1").unwrap();
+        assert!(stalemate.is_stalemate());
+        assert!(!stalemate.is_checkmate());
+    }
+
+    #[test]
+    fn rejects_bad_castles() {
+        // Crossing an attacked square
+        let position = Position::from_str("4kr2/8/8/8/8/8/8/R3K2R w KQ - 0 1").unwrap();
+        assert!(!position.is_pseudo_legal(PackedMove::new(named::E1, named::G1, None)));
+        assert!(position.is_pseudo_legal(PackedMove::new(named::E1, named::C1, None)));
+
+        // Blocked and without rights
+        let position = Position::from_str("r3k2r/8/8/8/8/8/8/RN2K2R w K - 0 1").unwrap();
+        assert!(!position.is_pseudo_legal(PackedMove::new(named::E1, named::C1, None)));
+        assert!(position.is_pseudo_legal(PackedMove::new(named::E1, named::G1, None)));
+
+        // Out of check
+        let position = Position::from_str("4r1k1/8/8/8/8/8/8/R3K2R w KQ - 0 1").unwrap();
+        assert!(!position.is_pseudo_legal(PackedMove::new(named::E1, named::G1, None)));
+    }
+}
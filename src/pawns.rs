@@ -0,0 +1,275 @@
+use crate::bitboard::Bitboard;
+use crate::side::Side;
+use crate::square::Square;
+use crate::square::masks::RANK_1;
+
+/// Squares in front of the pawns (towards promotion) not including the pawns themselves
+#[inline]
+pub fn front_span(pawns: u64, side: Side) -> u64 {
+    match side {
+        Side::White => pawns.north_shift().north_fill(),
+        Side::Black => pawns.south_shift().south_fill(),
+    }
+}
+
+/// Squares behind the pawns (towards their home rank) not including the pawns themselves
+#[inline]
+pub fn rear_span(pawns: u64, side: Side) -> u64 {
+    front_span(pawns, side.opposite())
+}
+
+/// Squares directly attacked by the pawns
+#[inline]
+pub fn attacks(pawns: u64, side: Side) -> u64 {
+    match side {
+        Side::White => pawns.north_east_shift() | pawns.north_west_shift(),
+        Side::Black => pawns.south_east_shift() | pawns.south_west_shift(),
+    }
+}
+
+/// Every square the pawns could ever attack while advancing
+#[inline]
+pub fn attack_span(pawns: u64, side: Side) -> u64 {
+    let front = front_span(pawns, side);
+    front.east_shift() | front.west_shift()
+}
+
+/// Files next to any file containing one of the pawns, as a file mask
+#[inline]
+pub fn adjacent_files(pawns: u64) -> u64 {
+    let files = pawns.file_fill();
+    (files.east_shift() | files.west_shift()) & !files
+}
+
+/// Squares that need to be free of enemy pawns for a pawn on `square` to be passed
+#[inline]
+pub fn passed_pawn_mask(square: Square, side: Side) -> u64 {
+    let pawn = square.mask();
+    front_span(pawn, side) | attack_span(pawn, side)
+}
+
+/// Pawns that cannot be stopped or captured by any enemy pawn on their way to promotion
+#[inline]
+pub fn passed_pawns(own_pawns: u64, enemy_pawns: u64, side: Side) -> u64 {
+    let enemy_side = side.opposite();
+    own_pawns & !(front_span(enemy_pawns, enemy_side) | attack_span(enemy_pawns, enemy_side))
+}
+
+/// Pawns without any friendly pawns on adjacent files
+#[inline]
+pub fn isolated_pawns(own_pawns: u64) -> u64 {
+    let files = own_pawns.file_fill();
+    own_pawns & !(files.east_shift() | files.west_shift())
+}
+
+/// Pawns sharing their file with at least one other friendly pawn
+#[inline]
+pub fn doubled_pawns(own_pawns: u64, side: Side) -> u64 {
+    own_pawns & (front_span(own_pawns, side) | rear_span(own_pawns, side))
+}
+
+/// Pawns whose stop square is attacked by an enemy pawn and can no longer be supported by a friendly pawn
+#[inline]
+pub fn backward_pawns(own_pawns: u64, enemy_pawns: u64, side: Side) -> u64 {
+    let stops = match side {
+        Side::White => own_pawns.north_shift(),
+        Side::Black => own_pawns.south_shift(),
+    };
+    let backward_stops = stops & attacks(enemy_pawns, side.opposite()) & !attack_span(own_pawns, side);
+
+    match side {
+        Side::White => backward_stops.south_shift(),
+        Side::Black => backward_stops.north_shift(),
+    }
+}
+
+/// Files without any pawns, as a file mask
+#[inline]
+pub fn open_files(white_pawns: u64, black_pawns: u64) -> u64 {
+    !(white_pawns | black_pawns).file_fill()
+}
+
+/// Files without friendly pawns that do contain enemy pawns, as a file mask
+#[inline]
+pub fn half_open_files(own_pawns: u64, enemy_pawns: u64) -> u64 {
+    enemy_pawns.file_fill() & !own_pawns.file_fill()
+}
+
+/// Number of groups of pawns on connected files
+#[inline]
+pub fn pawn_islands(own_pawns: u64) -> u32 {
+    let files = own_pawns.file_fill() & RANK_1;
+    // Count each file that has no pawns on the file to its west
+    (files & !files.east_shift()).count_ones()
+}
+
+/// If every pawn is blocked head on by an enemy pawn and none can capture, so no pawn can ever move again
+#[inline]
+pub fn is_locked(white_pawns: u64, black_pawns: u64) -> bool {
+    white_pawns.north_shift() == black_pawns
+        && attacks(white_pawns, Side::White) & black_pawns == 0
+        && attacks(black_pawns, Side::Black) & white_pawns == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bitboard::from_diagram;
+    use crate::square::{masks, named};
+
+    #[test]
+    fn front_span_works() {
+        assert_eq!(front_span(named::E4.mask(), Side::White), named::E5.mask() | named::E6.mask() | named::E7.mask() | named::E8.mask());
+        assert_eq!(front_span(named::E4.mask(), Side::Black), named::E3.mask() | named::E2.mask() | named::E1.mask());
+        assert_eq!(front_span(masks::RANK_2, Side::White), !(masks::RANK_1 | masks::RANK_2));
+        assert_eq!(front_span(0, Side::White), 0);
+    }
+
+    #[test]
+    fn rear_span_works() {
+        assert_eq!(rear_span(named::B3.mask(), Side::White), named::B2.mask() | named::B1.mask());
+        assert_eq!(rear_span(named::B6.mask(), Side::Black), named::B7.mask() | named::B8.mask());
+    }
+
+    #[test]
+    fn attacks_works() {
+        assert_eq!(attacks(named::E4.mask(), Side::White), named::D5.mask() | named::F5.mask());
+        assert_eq!(attacks(named::E4.mask(), Side::Black), named::D3.mask() | named::F3.mask());
+        assert_eq!(attacks(named::A2.mask(), Side::White), named::B3.mask());
+        assert_eq!(attacks(named::H7.mask(), Side::Black), named::G6.mask());
+    }
+
+    #[test]
+    fn attack_span_works() {
+        let expected = from_diagram("
+            . . . X . X . .
+            . . . X . X . .
+            . . . X . X . .
+            . . . . . . . .
+            . . . . . . . .
+            . . . . . . . .
+            . . . . . . . .
+            . . . . . . . .
+        ").unwrap();
+        assert_eq!(attack_span(named::E5.mask(), Side::White), expected);
+        assert_eq!(attack_span(named::H7.mask(), Side::Black), masks::G_FILE & !(masks::RANK_7 | masks::RANK_8));
+    }
+
+    #[test]
+    fn adjacent_files_works() {
+        assert_eq!(adjacent_files(named::A4.mask()), masks::neighbors::A_FILE);
+        assert_eq!(adjacent_files(named::E4.mask()), masks::neighbors::E_FILE);
+        assert_eq!(adjacent_files(named::H2.mask()), masks::neighbors::H_FILE);
+        assert_eq!(adjacent_files(named::C2.mask() | named::E7.mask()), masks::B_FILE | masks::D_FILE | masks::F_FILE);
+    }
+
+    #[test]
+    fn passed_pawn_mask_works() {
+        let expected = from_diagram("
+            . . . . . . . .
+            . . . . . . . .
+            . . . . . . . .
+            . . . . . . . .
+            . . . . . . . .
+            X X . . . . . .
+            X X . . . . . .
+            X X . . . . . .
+        ").unwrap();
+        assert_eq!(passed_pawn_mask(named::A4, Side::Black), expected);
+        assert_eq!(passed_pawn_mask(named::D7, Side::White), named::C8.mask() | named::D8.mask() | named::E8.mask());
+    }
+
+    #[test]
+    fn passed_pawns_works() {
+        let white = from_diagram("
+            . . . . . . . .
+            . . . . . . . .
+            . X . . . . . .
+            . . . . . . . .
+            . . . . X . . .
+            . . . . . . . X
+            . . X . . . . .
+            . . . . . . . .
+        ").unwrap();
+        let black = from_diagram("
+            . . . . . . . .
+            . . . . . X . .
+            . . . . . . . .
+            . . . . . . . .
+            X . . . . . . .
+            . . . . . . . .
+            . . . . . . . .
+            . . . . . . . .
+        ").unwrap();
+        // e4 is watched by f7, the a4 pawn only watches the b3, b2 and b1 squares
+        assert_eq!(passed_pawns(white, black, Side::White), named::B6.mask() | named::C2.mask() | named::H3.mask());
+        // b6 is behind a4 from black's perspective, f7 is watched by e4
+        assert_eq!(passed_pawns(black, white, Side::Black), named::A4.mask());
+    }
+
+    #[test]
+    fn isolated_pawns_works() {
+        let pawns = named::A2.mask() | named::C2.mask() | named::D3.mask() | named::F2.mask() | named::H2.mask();
+        assert_eq!(isolated_pawns(pawns), named::A2.mask() | named::F2.mask() | named::H2.mask());
+        assert_eq!(isolated_pawns(masks::RANK_2), 0);
+    }
+
+    #[test]
+    fn doubled_pawns_works() {
+        let pawns = named::C2.mask() | named::C4.mask() | named::E2.mask() | named::G3.mask() | named::G5.mask() | named::G6.mask();
+        let doubled = named::C2.mask() | named::C4.mask() | named::G3.mask() | named::G5.mask() | named::G6.mask();
+        assert_eq!(doubled_pawns(pawns, Side::White), doubled);
+        assert_eq!(doubled_pawns(pawns, Side::Black), doubled);
+        assert_eq!(doubled_pawns(masks::RANK_7, Side::Black), 0);
+    }
+
+    #[test]
+    fn backward_pawns_works() {
+        // d3 cannot advance past the e5 pawn's attack and has no pawns behind it on the c or e files
+        let white = named::C4.mask() | named::D3.mask();
+        let black = named::E5.mask() | named::C6.mask();
+        assert_eq!(backward_pawns(white, black, Side::White), named::D3.mask());
+        // With a pawn on e2 the d3 pawn can still be supported
+        assert_eq!(backward_pawns(white | named::E2.mask(), black, Side::White), 0);
+        // Mirrored for black
+        assert_eq!(backward_pawns(named::C5.mask() | named::D6.mask(), named::E4.mask(), Side::Black), named::D6.mask());
+    }
+
+    #[test]
+    fn open_files_works() {
+        let white = masks::RANK_2 & !(masks::D_FILE | masks::E_FILE);
+        let black = masks::RANK_7 & !(masks::E_FILE | masks::H_FILE);
+        assert_eq!(open_files(white, black), masks::E_FILE);
+        assert_eq!(open_files(0, 0), masks::ALL);
+    }
+
+    #[test]
+    fn half_open_files_works() {
+        let white = masks::RANK_2 & !(masks::D_FILE | masks::E_FILE);
+        let black = masks::RANK_7 & !(masks::E_FILE | masks::H_FILE);
+        assert_eq!(half_open_files(white, black), masks::D_FILE);
+        assert_eq!(half_open_files(black, white), masks::H_FILE);
+    }
+
+    #[test]
+    fn pawn_islands_works() {
+        assert_eq!(pawn_islands(0), 0);
+        assert_eq!(pawn_islands(masks::RANK_2), 1);
+        assert_eq!(pawn_islands(masks::RANK_2 & !masks::D_FILE), 2);
+        assert_eq!(pawn_islands(named::A2.mask() | named::C2.mask() | named::E2.mask() | named::G2.mask()), 4);
+        assert_eq!(pawn_islands(named::A2.mask() | named::A3.mask() | named::H7.mask()), 2);
+    }
+
+    #[test]
+    fn is_locked_works() {
+        let white = named::A4.mask() | named::B5.mask() | named::C4.mask();
+        let black = named::A5.mask() | named::B6.mask() | named::C5.mask();
+        // A chain where every capture square is empty or friendly
+        assert!(is_locked(white, black));
+        assert!(is_locked(0, 0));
+        // A pawn without a blocker can still advance
+        assert!(!is_locked(white | named::H2.mask(), black));
+        // d4 could capture on c5
+        assert!(!is_locked(white | named::D4.mask(), black | named::D5.mask()));
+    }
+}
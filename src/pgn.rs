@@ -0,0 +1,855 @@
+use std::fs;
+use std::io::{self, BufRead, BufReader, Write};
+use std::mem;
+use std::path::Path;
+use std::str::FromStr;
+use super::board::MakeUnmakeBoard;
+use super::chess_move::Move;
+use super::errors::{PgnError, PgnErrorKind};
+use super::game::{Annotation, Game, GameResult, Variation};
+use super::position::{FenMode, Position, START_FEN};
+use super::side::Side;
+
+/// Piece of PGN text
+#[derive(Clone, Debug, Eq, PartialEq)]
+enum Token {
+    TagStart,
+    Symbol(String),
+    MoveNumber,
+    Nag(u8),
+    Comment(String),
+    VariationStart,
+    VariationEnd,
+    Result(GameResult),
+}
+
+/// Variation being read, the main line goes straight into the game
+struct Line {
+    position: Position,
+    /// Position before the last move, where a variation replacing it starts
+    before_last: Option<Position>,
+    comment: Option<String>,
+    moves: Vec<Move>,
+    annotations: Vec<Annotation>,
+}
+
+impl Line {
+    fn new(position: Position) -> Self {
+        Line {
+            position,
+            before_last: None,
+            comment: None,
+            moves: Vec::new(),
+            annotations: Vec::new(),
+        }
+    }
+
+    fn play(&mut self, m: Move) {
+        self.before_last = Some(self.position);
+        self.position.make_move(&m);
+        self.moves.push(m);
+        self.annotations.push(Annotation::default());
+    }
+
+    /// Attach a comment to the last move, or the line itself before any move
+    fn add_comment(&mut self, text: String) {
+        let comment = match self.annotations.last_mut() {
+            Some(annotation) => &mut annotation.comment,
+            None => &mut self.comment,
+        };
+        append_comment(comment, text);
+    }
+}
+
+/// Add comment text, after any comment already there
+fn append_comment(comment: &mut Option<String>, text: String) {
+    match comment {
+        Some(existing) => {
+            existing.push(' ');
+            existing.push_str(&text);
+        },
+        None => *comment = Some(text),
+    }
+}
+
+/// Annotation of the last move in the innermost open variation, or of the game's last move
+fn last_annotation<'a>(game: &'a mut Game, variations: &'a mut [Line]) -> Option<&'a mut Annotation> {
+    match variations.last_mut() {
+        Some(line) => line.annotations.last_mut(),
+        None => {
+            let last = game.len().checked_sub(1)?;
+            game.annotation_mut(last)
+        },
+    }
+}
+
+/// Glyph for a move suffix like `!?`
+fn suffix_nag(suffix: &str) -> Option<u8> {
+    match suffix {
+        "!" => Some(1),
+        "?" => Some(2),
+        "!!" => Some(3),
+        "??" => Some(4),
+        "!?" => Some(5),
+        "?!" => Some(6),
+        _ => None,
+    }
+}
+
+/// Characters that can appear in SAN moves, move numbers and results
+fn is_symbol_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || matches!(c, '+' | '#' | '=' | ':' | '-' | '/' | '_')
+}
+
+/// Streaming reader turning PGN text into games one at a time
+///
+/// Only the current line is held in memory. After a malformed game the error is returned and reading resumes at the next game.
+/// Lines that aren't valid UTF-8 are read as Latin-1.
+pub struct PgnReader<R> {
+    reader: R,
+    /// Raw bytes of the current line, before decoding
+    bytes: Vec<u8>,
+    line: String,
+    /// Byte offset of the next character in the line
+    offset: usize,
+    line_number: usize,
+    /// Where the last token started, for error positions
+    token_line: usize,
+    token_column: usize,
+    /// The next game's first tag was already read while looking for the end of the last one
+    pending_tag: bool,
+    /// If the current game's movetext has started
+    in_movetext: bool,
+    finished: bool,
+}
+
+impl PgnReader<BufReader<fs::File>> {
+    /// Reader over a PGN file
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        Ok(PgnReader::new(BufReader::new(fs::File::open(path)?)))
+    }
+}
+
+impl<R: BufRead> PgnReader<R> {
+    pub fn new(reader: R) -> Self {
+        PgnReader {
+            reader,
+            bytes: Vec::new(),
+            line: String::new(),
+            offset: 0,
+            line_number: 0,
+            token_line: 0,
+            token_column: 0,
+            pending_tag: false,
+            in_movetext: false,
+            finished: false,
+        }
+    }
+
+    fn error(&self, kind: PgnErrorKind) -> PgnError {
+        PgnError { line: self.token_line, column: self.token_column, kind }
+    }
+
+    /// Error for the line that failed to be read
+    fn io_error(&self, error: io::Error) -> PgnError {
+        PgnError { line: self.line_number + 1, column: 1, kind: PgnErrorKind::Io(error) }
+    }
+
+    /// Read the next line, returning false at the end of the input
+    fn next_line(&mut self) -> io::Result<bool> {
+        self.line.clear();
+        self.offset = 0;
+        self.bytes.clear();
+        if self.reader.read_until(b'\n', &mut self.bytes)? == 0 {
+            return Ok(false);
+        }
+        self.line_number += 1;
+        match std::str::from_utf8(&self.bytes) {
+            Ok(text) => self.line.push_str(text),
+            // Latin-1 maps every byte to the code point of the same value
+            Err(_) => self.line.extend(self.bytes.iter().map(|&byte| byte as char)),
+        }
+        // Lines starting with % are escaped from parsing
+        if self.line.starts_with('%') {
+            self.offset = self.line.len();
+        }
+
+        Ok(true)
+    }
+
+    fn peek_char(&mut self) -> Result<Option<char>, PgnError> {
+        while self.offset >= self.line.len() {
+            if !self.next_line().map_err(|error| self.io_error(error))? {
+                return Ok(None);
+            }
+        }
+
+        Ok(self.line[self.offset..].chars().next())
+    }
+
+    #[inline]
+    fn bump(&mut self, c: char) {
+        self.offset += c.len_utf8();
+    }
+
+    /// Consume characters while they match, without crossing lines
+    fn take_while<F: Fn(char) -> bool>(&mut self, matches: F) -> String {
+        let rest = &self.line[self.offset..];
+        let length = rest.find(|c| !matches(c)).unwrap_or(rest.len());
+        let taken = rest[..length].to_string();
+        self.offset += length;
+
+        taken
+    }
+
+    fn skip_whitespace(&mut self) -> Result<(), PgnError> {
+        while let Some(c) = self.peek_char()? {
+            if !c.is_whitespace() {
+                break;
+            }
+            self.bump(c);
+        }
+
+        Ok(())
+    }
+
+    fn next_token(&mut self) -> Result<Option<Token>, PgnError> {
+        loop {
+            self.skip_whitespace()?;
+            let c = match self.peek_char()? {
+                Some(c) => c,
+                None => return Ok(None),
+            };
+            self.token_line = self.line_number;
+            self.token_column = self.offset + 1;
+            let token = match c {
+                '[' => {
+                    self.bump(c);
+                    Token::TagStart
+                },
+                '(' => {
+                    self.bump(c);
+                    Token::VariationStart
+                },
+                ')' => {
+                    self.bump(c);
+                    Token::VariationEnd
+                },
+                '*' => {
+                    self.bump(c);
+                    Token::Result(GameResult::Undecided)
+                },
+                // Dots after move numbers are read with them, stray ones like `1. ... e5` are skipped
+                '.' => {
+                    self.bump(c);
+                    continue;
+                },
+                '{' => {
+                    self.bump(c);
+                    Token::Comment(self.read_brace_comment()?)
+                },
+                ';' => {
+                    self.bump(c);
+                    let text = self.take_while(|c| c != '\n' && c != '\r');
+                    Token::Comment(text.trim().to_string())
+                },
+                '$' => {
+                    self.bump(c);
+                    let digits = self.take_while(|c| c.is_ascii_digit());
+                    Token::Nag(digits.parse().map_err(|_| self.error(PgnErrorKind::UnexpectedCharacter('$')))?)
+                },
+                '!' | '?' => {
+                    let suffix = self.take_while(|c| c == '!' || c == '?');
+                    Token::Nag(suffix_nag(&suffix).ok_or_else(|| self.error(PgnErrorKind::UnexpectedCharacter(c)))?)
+                },
+                _ if is_symbol_char(c) => {
+                    let symbol = self.take_while(is_symbol_char);
+                    if symbol.chars().all(|c| c.is_ascii_digit()) {
+                        self.take_while(|c| c == '.');
+                        Token::MoveNumber
+                    } else if let Ok(result) = GameResult::from_str(&symbol) {
+                        Token::Result(result)
+                    } else {
+                        Token::Symbol(symbol)
+                    }
+                },
+                _ => {
+                    self.bump(c);
+                    return Err(self.error(PgnErrorKind::UnexpectedCharacter(c)));
+                },
+            };
+
+            return Ok(Some(token));
+        }
+    }
+
+    /// Text up to the closing brace, which may be on a later line
+    fn read_brace_comment(&mut self) -> Result<String, PgnError> {
+        let mut text = String::new();
+        loop {
+            let part = self.take_while(|c| c != '}');
+            text.push_str(&part);
+            match self.peek_char()? {
+                Some('}') => {
+                    self.bump('}');
+                    return Ok(text.split_whitespace().collect::<Vec<_>>().join(" "));
+                },
+                Some(_) => {},
+                None => return Err(self.error(PgnErrorKind::UnexpectedEnd("comment"))),
+            }
+        }
+    }
+
+    /// Next character without moving past the end of the current line
+    #[inline]
+    fn peek_in_line(&self) -> Option<char> {
+        self.line[self.offset..].chars().next().filter(|&c| c != '\n' && c != '\r')
+    }
+
+    /// Rest of a `[Name "value"]` tag pair after the opening bracket, which has to end on the same line
+    fn read_tag(&mut self) -> Result<(String, String), PgnError> {
+        let is_space = |c: char| c == ' ' || c == '\t';
+        self.take_while(is_space);
+        let name = self.take_while(|c| c.is_ascii_alphanumeric() || c == '_');
+        self.take_while(is_space);
+        if name.is_empty() || self.peek_in_line() != Some('"') {
+            return Err(self.error(PgnErrorKind::InvalidTag));
+        }
+        self.bump('"');
+
+        let mut value = String::new();
+        loop {
+            match self.peek_in_line() {
+                Some('"') => {
+                    self.bump('"');
+                    break;
+                },
+                Some('\\') => {
+                    self.bump('\\');
+                    match self.peek_in_line() {
+                        Some(escaped) => {
+                            self.bump(escaped);
+                            value.push(escaped);
+                        },
+                        None => return Err(self.error(PgnErrorKind::UnexpectedEnd("tag"))),
+                    }
+                },
+                Some(c) => {
+                    self.bump(c);
+                    value.push(c);
+                },
+                None => return Err(self.error(PgnErrorKind::UnexpectedEnd("tag"))),
+            }
+        }
+
+        self.take_while(is_space);
+        if self.peek_in_line() != Some(']') {
+            return Err(self.error(PgnErrorKind::InvalidTag));
+        }
+        self.bump(']');
+
+        Ok((name, value))
+    }
+
+    /// Read the next game, or nothing at the end of the input
+    fn read_game(&mut self) -> Result<Option<Game>, PgnError> {
+        let mut tags = Vec::new();
+        let mut token = if mem::replace(&mut self.pending_tag, false) { Some(Token::TagStart) } else { self.next_token()? };
+        while token == Some(Token::TagStart) {
+            tags.push(self.read_tag()?);
+            token = self.next_token()?;
+        }
+        if tags.is_empty() && token.is_none() {
+            return Ok(None);
+        }
+        self.in_movetext = true;
+
+        let start = match tags.iter().find(|(name, _)| name == "FEN") {
+            Some((_, fen)) => Position::from_fen(fen, FenMode::Lenient).map_err(|error| self.error(PgnErrorKind::InvalidFen(error)))?,
+            None => Position::start(),
+        };
+        let mut game = Game::new(start);
+        for (name, value) in tags.iter() {
+            game.set_tag(name, value);
+        }
+        if let Some(result) = game.tag("Result").and_then(|result| GameResult::from_str(result).ok()) {
+            game.result = result;
+        }
+
+        // Main line moves are played into the game as they are read, only open variations are kept aside
+        let mut before_last = None;
+        let mut variations: Vec<Line> = Vec::new();
+        loop {
+            match token {
+                None => break,
+                Some(Token::TagStart) => {
+                    // The next game started without this one giving a result
+                    self.pending_tag = true;
+                    break;
+                },
+                Some(Token::Result(result)) => {
+                    if !variations.is_empty() {
+                        return Err(self.error(PgnErrorKind::UnbalancedVariation));
+                    }
+                    game.result = result;
+                    break;
+                },
+                Some(Token::Symbol(san)) => {
+                    let position = match variations.last() {
+                        Some(line) => line.position,
+                        None => *game.position(),
+                    };
+                    let m = position.parse_san(&san).map_err(|error| self.error(PgnErrorKind::InvalidMove(error)))?;
+                    match variations.last_mut() {
+                        Some(line) => line.play(m),
+                        None => {
+                            before_last = Some(position);
+                            game.play_unchecked(m);
+                        },
+                    }
+                },
+                Some(Token::MoveNumber) => {},
+                Some(Token::Nag(nag)) => {
+                    if let Some(annotation) = last_annotation(&mut game, &mut variations) {
+                        annotation.nags.push(nag);
+                    }
+                },
+                Some(Token::Comment(text)) => match variations.last_mut() {
+                    Some(line) => line.add_comment(text),
+                    None => match last_annotation(&mut game, &mut variations) {
+                        Some(annotation) => append_comment(&mut annotation.comment, text),
+                        None => append_comment(&mut game.comment, text),
+                    },
+                },
+                Some(Token::VariationStart) => {
+                    let start = match variations.last() {
+                        Some(line) => line.before_last,
+                        None => before_last,
+                    };
+                    let start = start.ok_or_else(|| self.error(PgnErrorKind::VariationWithoutMove))?;
+                    variations.push(Line::new(start));
+                },
+                Some(Token::VariationEnd) => {
+                    let ended = variations.pop().ok_or_else(|| self.error(PgnErrorKind::UnbalancedVariation))?;
+                    let variation = Variation { comment: ended.comment, moves: ended.moves, annotations: ended.annotations };
+                    last_annotation(&mut game, &mut variations).expect("variations start after a move").variations.push(variation);
+                },
+            }
+            token = self.next_token()?;
+        }
+        if !variations.is_empty() {
+            return Err(self.error(PgnErrorKind::UnexpectedEnd("variation")));
+        }
+
+        Ok(Some(game))
+    }
+
+    /// Drop input up to the tags of the next game
+    fn skip_game(&mut self) -> io::Result<()> {
+        // The next game's first tag was already reached
+        if self.pending_tag {
+            return Ok(());
+        }
+        self.offset = self.line.len();
+        while self.next_line()? {
+            let trimmed = self.line.trim_start();
+            if trimmed.starts_with('[') {
+                if self.in_movetext {
+                    return Ok(());
+                }
+            } else if !trimmed.is_empty() {
+                self.in_movetext = true;
+            }
+            self.offset = self.line.len();
+        }
+
+        Ok(())
+    }
+}
+
+impl<R: BufRead> Iterator for PgnReader<R> {
+    type Item = Result<Game, PgnError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.finished {
+            return None;
+        }
+        self.in_movetext = false;
+        match self.read_game() {
+            Ok(Some(game)) => Some(Ok(game)),
+            Ok(None) => {
+                self.finished = true;
+                None
+            },
+            Err(error) => {
+                let stop = match error.kind {
+                    PgnErrorKind::Io(_) => true,
+                    _ => self.skip_game().is_err(),
+                };
+                self.finished = stop;
+                Some(Err(error))
+            },
+        }
+    }
+}
+
+/// Tags every exported game starts with, in order, and their value when unknown
+const SEVEN_TAG_ROSTER: [(&str, &str); 7] = [
+    ("Event", "?"),
+    ("Site", "?"),
+    ("Date", "????.??.??"),
+    ("Round", "?"),
+    ("White", "?"),
+    ("Black", "?"),
+    ("Result", "*"),
+];
+
+/// Longest line of exported movetext
+const MAX_LINE_LENGTH: usize = 80;
+
+/// Tag value with quotes and backslashes escaped
+fn escape_tag_value(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+/// Words of a brace comment, split so it can wrap over lines
+///
+/// Closing braces can't be escaped so they are dropped. Words starting with `%` stay with the word before them, since readers skip lines starting with `%`.
+fn push_comment(tokens: &mut Vec<String>, comment: &str) {
+    let mut words: Vec<String> = Vec::new();
+    for word in comment.split_whitespace().map(|word| word.replace('}', "")).filter(|word| !word.is_empty()) {
+        match words.last_mut() {
+            Some(last) if word.starts_with('%') => {
+                last.push(' ');
+                last.push_str(&word);
+            },
+            _ => words.push(word),
+        }
+    }
+    match words.len() {
+        0 => tokens.push("{}".to_string()),
+        1 => tokens.push(format!("{{{}}}", words[0])),
+        _ => {
+            tokens.push(format!("{{{}", words[0]));
+            tokens.extend(words[1..words.len() - 1].iter().cloned());
+            tokens.push(format!("{}}}", words[words.len() - 1]));
+        },
+    }
+}
+
+/// Movetext tokens for a line of moves and everything attached to them
+fn push_line(tokens: &mut Vec<String>, start: &Position, comment: Option<&str>, moves: &[Move], annotations: &[Annotation]) {
+    if let Some(comment) = comment {
+        push_comment(tokens, comment);
+    }
+    let mut position = *start;
+    // Black moves need their number at the start of a line or after an interruption
+    let mut needs_number = true;
+    for (m, annotation) in moves.iter().zip(annotations.iter()) {
+        if position.side == Side::White {
+            tokens.push(format!("{}.", position.fullmove_count));
+        } else if needs_number {
+            tokens.push(format!("{}...", position.fullmove_count));
+        }
+        tokens.push(position.san(m));
+        tokens.extend(annotation.nags.iter().map(|nag| format!("${}", nag)));
+        needs_number = false;
+        if let Some(comment) = &annotation.comment {
+            push_comment(tokens, comment);
+            needs_number = true;
+        }
+        for variation in annotation.variations.iter() {
+            let mut variation_tokens = Vec::new();
+            push_line(&mut variation_tokens, &position, variation.comment.as_deref(), &variation.moves, &variation.annotations);
+            if variation_tokens.is_empty() {
+                variation_tokens.push(String::new());
+            }
+            variation_tokens[0].insert(0, '(');
+            variation_tokens.last_mut().expect("variation has a token").push(')');
+            tokens.extend(variation_tokens);
+            needs_number = true;
+        }
+        position.make_move(m);
+    }
+}
+
+impl Game {
+    /// Write the game as PGN with the Seven Tag Roster first and movetext wrapped at 80 columns
+    pub fn write_pgn<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        for &(name, unknown) in SEVEN_TAG_ROSTER.iter() {
+            let value = match name {
+                "Result" => self.result.to_string(),
+                _ => self.tag(name).unwrap_or(unknown).to_string(),
+            };
+            writeln!(writer, "[{} \"{}\"]", name, escape_tag_value(&value))?;
+        }
+        let start_fen = String::from(self.start_position());
+        if start_fen != START_FEN {
+            writeln!(writer, "[SetUp \"1\"]")?;
+            writeln!(writer, "[FEN \"{}\"]", start_fen)?;
+        }
+        for (name, value) in self.tags().iter() {
+            let exported = SEVEN_TAG_ROSTER.iter().any(|&(roster_name, _)| roster_name == name) || name == "SetUp" || name == "FEN";
+            if !exported {
+                writeln!(writer, "[{} \"{}\"]", name, escape_tag_value(value))?;
+            }
+        }
+        writeln!(writer)?;
+
+        let mut tokens = Vec::new();
+        push_line(&mut tokens, self.start_position(), self.comment.as_deref(), self.moves(), self.annotations());
+        tokens.push(self.result.to_string());
+        let mut line = String::new();
+        for token in tokens {
+            if !line.is_empty() && line.len() + 1 + token.len() > MAX_LINE_LENGTH {
+                writeln!(writer, "{}", line)?;
+                line.clear();
+            }
+            if !line.is_empty() {
+                line.push(' ');
+            }
+            line.push_str(&token);
+        }
+        writeln!(writer, "{}", line)
+    }
+
+    /// Game as a PGN string
+    pub fn to_pgn(&self) -> String {
+        let mut pgn = Vec::new();
+        self.write_pgn(&mut pgn).expect("writing to a vec can't fail");
+
+        String::from_utf8(pgn).expect("pgn is written from strings")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chess_move::UCIMove;
+    use std::str::FromStr;
+
+    fn read(pgn: &str) -> Vec<Result<Game, PgnError>> {
+        PgnReader::new(pgn.as_bytes()).collect()
+    }
+
+    fn uci_moves(moves: &[Move]) -> Vec<String> {
+        moves.iter().map(|m| m.to_uci()).collect()
+    }
+
+    const OPERA: &str = r#"[Event "Paris"]
+[Site "Paris FRA"]
+[Date "1858.??.??"]
+[Round "?"]
+[White "Paul Morphy"]
+[Black "Duke Karl / Count Isouard"]
+[Result "1-0"]
+
+1.e4 e5 2.Nf3 d6 3.d4 Bg4 {This is a weak move already.} 4.dxe5 Bxf3 5.Qxf3 dxe5 6.Bc4 Nf6 7.Qb3 qe7
+"#;
+
+    #[test]
+    fn reads_tags_and_moves() {
+        let pgn = r#"[Event "Casual \"game\""]
+[Result "0-1"]
+
+1. f3 e5 2. g4?? Qh4# 0-1
+"#;
+        let games = read(pgn);
+        assert_eq!(games.len(), 1);
+        let game = games[0].as_ref().unwrap();
+        assert_eq!(game.tag("Event"), Some("Casual \"game\""));
+        assert_eq!(game.result, GameResult::BlackWins);
+        assert_eq!(uci_moves(game.moves()), vec!["f2f3", "e7e5", "g2g4", "d8h4"]);
+        assert_eq!(game.annotations()[2].nags, vec![4]);
+        assert!(game.position().is_checkmate());
+    }
+
+    #[test]
+    fn reads_comments_nags_and_variations() {
+        let pgn = "{Opening} 1. e4 {best by test} ; really\n e5 $1 (1... c5 {Sicilian} 2. Nf3 (2. c3) d6) (1... e6) 2. Nf3 *";
+        let game = read(pgn).remove(0).unwrap();
+        assert_eq!(game.comment.as_deref(), Some("Opening"));
+        assert_eq!(game.annotations()[0].comment.as_deref(), Some("best by test really"));
+        assert_eq!(game.annotations()[1].nags, vec![1]);
+        assert_eq!(game.result, GameResult::Undecided);
+
+        let variations = &game.annotations()[1].variations;
+        assert_eq!(variations.len(), 2);
+        assert_eq!(uci_moves(&variations[0].moves), vec!["c7c5", "g1f3", "d7d6"]);
+        assert_eq!(variations[0].annotations[0].comment.as_deref(), Some("Sicilian"));
+        assert_eq!(uci_moves(&variations[0].annotations[1].variations[0].moves), vec!["c2c3"]);
+        assert_eq!(uci_moves(&variations[1].moves), vec!["e7e6"]);
+    }
+
+    #[test]
+    fn reads_long_games() {
+        let mut pgn = String::new();
+        for number in 0..300 {
+            pgn.push_str(&format!("{}. Nf3 Nf6 {}. Ng1 {{back}} Ng8 $6 ", 2 * number + 1, 2 * number + 2));
+        }
+        pgn.push_str("1/2-1/2");
+        let game = read(&pgn).remove(0).unwrap();
+        assert_eq!(game.len(), 1200);
+        assert_eq!(game.ply(), 1200);
+        assert_eq!(game.key_history().len(), 1200);
+        assert_eq!(game.annotations()[2].comment.as_deref(), Some("back"));
+        assert_eq!(game.annotations()[1199].nags, vec![6]);
+        assert_eq!(game.position().halfmove_clock, Some(255));
+        assert_eq!(String::from(game.position()), "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 255 601");
+    }
+
+    #[test]
+    fn reads_setup_positions() {
+        let pgn = "[SetUp \"1\"]\n[FEN \"4k3/8/8/8/8/8/4P3/4K3 b - - 0 1\"]\n\n1... Kd7 2. e4 1/2-1/2\n";
+        let game = read(pgn).remove(0).unwrap();
+        assert_eq!(String::from(game.start_position()), "4k3/8/8/8/8/8/4P3/4K3 b - - 0 1");
+        assert_eq!(uci_moves(game.moves()), vec!["e8d7", "e2e4"]);
+        assert_eq!(game.result, GameResult::Draw);
+    }
+
+    #[test]
+    fn skips_malformed_games() {
+        let pgn = format!("{}\n[Event \"Next\"]\n\n1. d4 d5 *\n\n[Event\n\n1. e4 *\n\n[Event \"Last\"]\n1. c4 (\n", OPERA);
+        let games = read(&pgn);
+        assert_eq!(games.len(), 4);
+
+        let error = games[0].as_ref().err().unwrap();
+        assert_eq!((error.line, error.column), (9, 98));
+        match &error.kind {
+            PgnErrorKind::InvalidMove(_) => {},
+            kind => panic!("unexpected error {:?}", kind),
+        }
+
+        assert_eq!(games[1].as_ref().unwrap().tag("Event"), Some("Next"));
+        match games[2].as_ref().err().unwrap().kind {
+            PgnErrorKind::InvalidTag => {},
+            ref kind => panic!("unexpected error {:?}", kind),
+        }
+        match games[3].as_ref().err().unwrap().kind {
+            PgnErrorKind::UnexpectedEnd(_) => {},
+            ref kind => panic!("unexpected error {:?}", kind),
+        }
+
+        // Tags of the next game that ended a malformed one are kept
+        let games = read("1. c4 (\n[Event \"X\"]\n[Site \"Y\"]\n\n1. d4 *");
+        assert_eq!(games.len(), 2);
+        assert!(games[0].is_err());
+        let game = games[1].as_ref().unwrap();
+        assert_eq!((game.tag("Event"), game.tag("Site")), (Some("X"), Some("Y")));
+
+        let games = read("1. c4 (\n[Event \"X\"]\n\n1. d4 *\n\n[Event \"Z\"]\n\n1. e4 *");
+        assert_eq!(games.len(), 3);
+        assert!(games[0].is_err());
+        assert_eq!(games[1].as_ref().unwrap().tag("Event"), Some("X"));
+        assert_eq!(uci_moves(games[1].as_ref().unwrap().moves()), vec!["d2d4"]);
+        assert_eq!(games[2].as_ref().unwrap().tag("Event"), Some("Z"));
+    }
+
+    #[test]
+    fn reads_latin1_lines() {
+        let mut pgn = b"[White \"R".to_vec();
+        pgn.push(0xE9);
+        pgn.extend_from_slice(b"ti\"]\n\n1. Nf3 {d\xE9but} *\n\n[Event \"Next\"]\n\n1. e4 *\n");
+        let games: Vec<_> = PgnReader::new(pgn.as_slice()).collect();
+        assert_eq!(games.len(), 2);
+        let game = games[0].as_ref().unwrap();
+        assert_eq!(game.tag("White"), Some("R\u{e9}ti"));
+        assert_eq!(game.annotations()[0].comment.as_deref(), Some("d\u{e9}but"));
+        assert_eq!(games[1].as_ref().unwrap().tag("Event"), Some("Next"));
+    }
+
+    #[test]
+    fn read_errors_report_the_failing_line() {
+        struct Failing;
+        impl io::Read for Failing {
+            fn read(&mut self, _: &mut [u8]) -> io::Result<usize> {
+                Err(io::Error::new(io::ErrorKind::Other, "disk on fire"))
+            }
+        }
+
+        let reader = io::BufReader::new(io::Read::chain(&b"[Event \"A\"]\n\n"[..], Failing));
+        let games: Vec<_> = PgnReader::new(reader).collect();
+        assert_eq!(games.len(), 1);
+        let error = games[0].as_ref().err().unwrap();
+        assert_eq!((error.line, error.column), (3, 1));
+        assert!(matches!(error.kind, PgnErrorKind::Io(_)));
+    }
+
+    #[test]
+    fn writes_pgn() {
+        let pgn = "[White \"Morphy, \\\"Paul\\\"\"]\n[Annotator \"Steinitz\"]\n\n{Opening} 1. e4 e5 $1 {main} (1... c5 2. Nf3 (2. c3) d6) 2. Nf3 1-0";
+        let game = read(pgn).remove(0).unwrap();
+        let expected = "[Event \"?\"]
+[Site \"?\"]
+[Date \"????.??.??\"]
+[Round \"?\"]
+[White \"Morphy, \\\"Paul\\\"\"]
+[Black \"?\"]
+[Result \"1-0\"]
+[Annotator \"Steinitz\"]
+
+{Opening} 1. e4 e5 $1 {main} (1... c5 2. Nf3 (2. c3) 2... d6) 2. Nf3 1-0
+";
+        assert_eq!(game.to_pgn(), expected);
+    }
+
+    #[test]
+    fn writes_setup_and_black_first_moves() {
+        let mut game = Game::new(Position::from_str("4k3/8/8/8/8/8/4P3/4K3 b - - 0 12").unwrap());
+        for san in ["Kd7", "e4", "Ke6"].iter() {
+            let m = game.position().parse_san(san).unwrap();
+            game.play(m).unwrap();
+        }
+        let pgn = game.to_pgn();
+        assert!(pgn.contains("[SetUp \"1\"]\n[FEN \"4k3/8/8/8/8/8/4P3/4K3 b - - 0 12\"]\n"));
+        assert!(pgn.ends_with("\n12... Kd7 13. e4 Ke6 *\n"));
+        assert!(!Game::default().to_pgn().contains("FEN"));
+    }
+
+    #[test]
+    fn written_pgn_round_trips() {
+        let pgn = "[Event \"Long\"]\n\n1. e4 e5 2. Nf3 Nc6 3. Bb5 {The Ruy Lopez, one of the oldest and most classical openings in chess} a6 \
+            (3... Nf6 $2 {The Berlin} 4. O-O (4. d3 Bc5 (4... d6)) Nxe4) 4. Ba4 Nf6 5. O-O Be7 6. Re1 b5 7. Bb3 d6 8. c3 O-O \
+            9. h3 Nb8 10. d4 Nbd7 11. c4 c6 12. cxb5 axb5 13. Nc3 Bb7 14. Bg5 b4 15. Nb1 h6 16. Bh4 c5 17. dxe5 Nxe4 \
+            18. Bxe7 Qxe7 19. exd6 Qf6 20. Nbd2 Nxd6 21. Nc4 Nxc4 22. Bxc4 Nb6 23. Ne5 Rae8 24. Bxf7+ Rxf7 25. Nxf7 Rxe1+ \
+            26. Qxe1 Kxf7 27. Qe3 Qg5 28. Qxg5 hxg5 29. b3 Ke6 30. a3 Kd6 31. axb4 cxb4 32. Ra5 Nd5 33. f3 Bc8 34. Kf2 Bf5 \
+            35. Ra7 g6 36. Ra6+ Kc5 37. Ke1 Nf4 38. g3 Nxh3 39. Kd2 Kb5 40. Rd6 Kc5 41. Ra6 Nf2 42. g4 Bd3 43. Re6 1/2-1/2";
+        let game = read(pgn).remove(0).unwrap();
+        assert_eq!(game.len(), 85);
+        let written = game.to_pgn();
+        assert!(written.lines().all(|line| line.len() <= MAX_LINE_LENGTH));
+
+        let reread = read(&written).remove(0).unwrap();
+        assert_eq!(uci_moves(reread.moves()), uci_moves(game.moves()));
+        assert_eq!(reread.annotations(), game.annotations());
+        assert_eq!(reread.result, GameResult::Draw);
+        assert_eq!(reread.to_pgn(), written);
+    }
+
+    #[test]
+    fn written_comments_round_trip() {
+        let mut game = read("1. e4 e5 *").remove(0).unwrap();
+        let long: Vec<&str> = std::iter::repeat("word %cal").take(30).collect();
+        game.comment = Some(format!("{{nested}} braces}} {}", long.join(" ")));
+        game.annotation_mut(0).unwrap().comment = Some("%clk 0:03:00 then } more".to_string());
+        game.annotation_mut(1).unwrap().comment = Some("}".to_string());
+        let written = game.to_pgn();
+        assert!(written.lines().all(|line| !line.starts_with('%')));
+
+        let reread = read(&written).remove(0).unwrap();
+        assert_eq!(reread.comment, Some(format!("{{nested braces {}", long.join(" "))));
+        assert_eq!(reread.annotations()[0].comment.as_deref(), Some("%clk 0:03:00 then more"));
+        assert_eq!(reread.annotations()[1].comment.as_deref(), Some(""));
+        assert_eq!(uci_moves(reread.moves()), vec!["e2e4", "e7e5"]);
+        assert_eq!(reread.to_pgn(), written);
+    }
+
+    #[test]
+    fn games_without_results_end_at_the_next_tags() {
+        let games = read("1. e4\n\n[Event \"Second\"]\n1. d4 *");
+        assert_eq!(games.len(), 2);
+        assert_eq!(games[0].as_ref().unwrap().len(), 1);
+        assert_eq!(games[1].as_ref().unwrap().tag("Event"), Some("Second"));
+        assert!(read("\n\n").is_empty());
+    }
+}
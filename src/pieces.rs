@@ -2,7 +2,7 @@
 
 use super::side::Side;
 
-#[derive(Eq, PartialEq, Hash, Copy, Clone, Debug)]
+#[derive(Eq, PartialEq, Hash, Copy, Clone, Debug, Default)]
 pub enum ColoredPiece {
     WPawn,
     WBishop,
@@ -16,10 +16,11 @@ pub enum ColoredPiece {
     BKing,
     BKnight,
     BQueen,
+    #[default]
     None,
 }
 
-#[derive(Eq, PartialEq, Hash, Copy, Clone, Debug)]
+#[derive(Eq, PartialEq, Hash, Copy, Clone, Debug, Default)]
 pub enum Piece {
     Pawn,
     Bishop,
@@ -27,17 +28,10 @@ pub enum Piece {
     King,
     Knight,
     Queen,
+    #[default]
     None,
 }
 
-impl Default for Piece {
-    fn default() -> Self { Piece::None }
-}
-
-impl Default for ColoredPiece {
-    fn default() -> Self { ColoredPiece::None }
-}
-
 impl From<ColoredPiece> for usize {
     fn from(colored_piece: ColoredPiece) -> usize {
         match colored_piece {
@@ -92,6 +86,24 @@ impl ColoredPiece {
             ColoredPiece::None => Piece::None
         }
     }
+    /// Same piece type owned by the other side
+    pub fn color_flipped(&self) -> ColoredPiece {
+        match self {
+            ColoredPiece::WPawn => ColoredPiece::BPawn,
+            ColoredPiece::WBishop => ColoredPiece::BBishop,
+            ColoredPiece::WRook => ColoredPiece::BRook,
+            ColoredPiece::WKing => ColoredPiece::BKing,
+            ColoredPiece::WKnight => ColoredPiece::BKnight,
+            ColoredPiece::WQueen => ColoredPiece::BQueen,
+            ColoredPiece::BPawn => ColoredPiece::WPawn,
+            ColoredPiece::BBishop => ColoredPiece::WBishop,
+            ColoredPiece::BRook => ColoredPiece::WRook,
+            ColoredPiece::BKing => ColoredPiece::WKing,
+            ColoredPiece::BKnight => ColoredPiece::WKnight,
+            ColoredPiece::BQueen => ColoredPiece::WQueen,
+            ColoredPiece::None => ColoredPiece::None
+        }
+    }
 }
 
 impl From<char> for ColoredPiece {
@@ -4,12 +4,18 @@ use std::convert::{TryFrom};
 
 use super::side::Side;
 use super::castles::CastlePermissions;
-use super::square::Square;
-use super::pieces::{ColoredPiece};
+use super::square::{Square, Rank, RANK_CHARS};
+use super::square::{self, masks, named};
+use super::pieces::{ColoredPiece, Piece};
 use super::errors;
+use super::attacks;
+use super::bitboard::Bitboard;
+use super::zobrist::{SubKeys, ZobristHashable, ZobristKey};
 use super::pieces::PieceRepr;
-use crate::bitboard::Bitboard;
+#[cfg(debug_assertions)]
+use crate::bitboard::BitboardDiagram;
 use std::borrow::BorrowMut;
+use std::str::FromStr;
 
 #[derive(Copy, Clone)]
 pub struct Position {
@@ -20,6 +26,8 @@ pub struct Position {
     pub enpassant_square: Option<Square>,
     pub squares: [ColoredPiece; 64],
     piece_masks: [u64; 12],
+    pub(crate) hash: ZobristKey,
+    pub(crate) sub_keys: SubKeys,
 }
 
 impl Position {
@@ -63,6 +71,188 @@ impl Position {
         }
     }
 
+    /// Occupied mask for a side
+    #[inline]
+    pub fn side_mask(&self, side: Side) -> u64 {
+        match side {
+            Side::White => self.white_mask(),
+            Side::Black => self.black_mask(),
+        }
+    }
+    /// Square of a side's king (the lowest one if there are several)
+    #[inline]
+    pub fn king_square(&self, side: Side) -> Option<Square> {
+        let kings = self.piece_mask(Piece::King.color(side));
+        if kings == 0 {
+            None
+        } else {
+            Some(Square(kings.trailing_zeros() as u8))
+        }
+    }
+    /// Every square attacked by a side
+    pub fn attacked_mask(&self, side: Side) -> u64 {
+        let empty = self.empty_mask();
+        let diagonal_sliders = self.piece_mask(Piece::Bishop.color(side)) | self.piece_mask(Piece::Queen.color(side));
+        let straight_sliders = self.piece_mask(Piece::Rook.color(side)) | self.piece_mask(Piece::Queen.color(side));
+
+        attacks::pawn_attacks(self.piece_mask(Piece::Pawn.color(side)), side)
+            | attacks::knight_attacks(self.piece_mask(Piece::Knight.color(side)))
+            | attacks::king_attacks(self.piece_mask(Piece::King.color(side)))
+            | attacks::bishop_attacks(diagonal_sliders, empty)
+            | attacks::rook_attacks(straight_sliders, empty)
+    }
+    /// If a square is attacked by any piece of a side
+    #[inline]
+    pub fn is_square_attacked(&self, square: Square, by: Side) -> bool {
+        self.attacked_mask(by) & square.mask() != 0
+    }
+    /// If a side's king is attacked
+    #[inline]
+    pub fn in_check(&self, side: Side) -> bool {
+        self.attacked_mask(side.opposite()) & self.piece_mask(Piece::King.color(side)) != 0
+    }
+
+    /// Check the position is legal and internally consistent, returning every problem found
+    pub fn validate(&self) -> Result<(), Vec<errors::PositionError>> {
+        use errors::PositionError;
+
+        let mut problems = Vec::new();
+
+        // Squares list and piece masks have to agree
+        for offset in 0..64u8 {
+            let square = Square(offset);
+            let piece = self.squares[offset as usize];
+            let mut owners = self.piece_masks.iter().enumerate().filter(|(_, &mask)| mask & square.mask() != 0).map(|(index, _)| index);
+            let consistent = match (piece, owners.next(), owners.next()) {
+                (ColoredPiece::None, None, _) => true,
+                (_, Some(index), None) => index == piece as usize,
+                _ => false,
+            };
+            if !consistent {
+                problems.push(PositionError::InconsistentSquare(square));
+            }
+        }
+
+        for &side in &[Side::White, Side::Black] {
+            let kings = self.piece_mask(Piece::King.color(side)).count_ones();
+            if kings == 0 {
+                problems.push(PositionError::MissingKing(side));
+            } else if kings > 1 {
+                problems.push(PositionError::TooManyKings(side, kings));
+            }
+
+            let pieces = self.side_mask(side).count_ones();
+            if pieces > 16 {
+                problems.push(PositionError::TooManyPieces(side, pieces));
+            }
+            let pawns = self.piece_mask(Piece::Pawn.color(side)).count_ones();
+            if pawns > 8 {
+                problems.push(PositionError::TooManyPawns(side, pawns));
+            }
+            // Pieces beyond the starting set can only come from promoted pawns
+            let extra = |piece: Piece, starting: u32| self.piece_mask(piece.color(side)).count_ones().saturating_sub(starting);
+            let promoted = extra(Piece::Queen, 1) + extra(Piece::Rook, 2) + extra(Piece::Bishop, 2) + extra(Piece::Knight, 2);
+            if pawns <= 8 && promoted > 8 - pawns {
+                problems.push(PositionError::TooManyPromotedPieces(side));
+            }
+        }
+
+        let back_rank_pawns = (self.piece_mask(ColoredPiece::WPawn) | self.piece_mask(ColoredPiece::BPawn)) & (masks::RANK_1 | masks::RANK_8);
+        problems.extend(square::mask_to_square_iter(back_rank_pawns).map(PositionError::PawnOnBackRank));
+
+        if self.in_check(self.side.opposite()) {
+            problems.push(PositionError::OpponentInCheck(self.side.opposite()));
+        }
+
+        let castle_requirements = [
+            (CastlePermissions::WHITE_KING, ColoredPiece::WKing, named::E1, ColoredPiece::WRook, named::H1),
+            (CastlePermissions::WHITE_QUEEN, ColoredPiece::WKing, named::E1, ColoredPiece::WRook, named::A1),
+            (CastlePermissions::BLACK_KING, ColoredPiece::BKing, named::E8, ColoredPiece::BRook, named::H8),
+            (CastlePermissions::BLACK_QUEEN, ColoredPiece::BKing, named::E8, ColoredPiece::BRook, named::A8),
+        ];
+        let mut invalid_castles = CastlePermissions::NONE;
+        for &(castle, king, king_square, rook, rook_square) in &castle_requirements {
+            let in_place = self.squares[king_square.offset() as usize] == king && self.squares[rook_square.offset() as usize] == rook;
+            if self.castle_rights.contains(castle) && !in_place {
+                invalid_castles |= castle;
+            }
+        }
+        if !invalid_castles.is_empty() {
+            problems.push(PositionError::InvalidCastleRights(invalid_castles));
+        }
+
+        if let Some(enpassant_square) = self.enpassant_square {
+            if !self.is_valid_enpassant_square(enpassant_square) {
+                problems.push(PositionError::InvalidEnPassant(enpassant_square));
+            }
+        }
+
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(problems)
+        }
+    }
+
+    /// If an en passant square is directly behind a pawn that could have just double jumped
+    fn is_valid_enpassant_square(&self, enpassant_square: Square) -> bool {
+        let last_mover = self.side.opposite();
+        // Direction the side that just moved pushes its pawns
+        let forward = if last_mover == Side::White { 1 } else { -1 };
+        if enpassant_square.relative_rank(last_mover) != Rank::Third {
+            return false;
+        }
+        match (enpassant_square.translate(0, -forward), enpassant_square.translate(0, forward)) {
+            (Some(origin), Some(pawn)) => {
+                self.squares[pawn.offset() as usize] == Piece::Pawn.color(last_mover)
+                    && self.squares[enpassant_square.offset() as usize] == ColoredPiece::None
+                    && self.squares[origin.offset() as usize] == ColoredPiece::None
+            },
+            _ => false,
+        }
+    }
+
+    /// Same position with colors swapped and ranks mirrored, as seen by the other side
+    pub fn color_flipped(&self) -> Self {
+        let mut flipped = *self;
+        flipped.side = self.side.opposite();
+        flipped.castle_rights = self.castle_rights.color_flipped();
+        flipped.enpassant_square = self.enpassant_square.map(Square::flip_vertical);
+        for (offset, &piece) in self.squares.iter().enumerate() {
+            flipped.squares[Square(offset as u8).flip_vertical().offset() as usize] = piece.color_flipped();
+        }
+        for (index, &mask) in self.piece_masks.iter().enumerate() {
+            flipped.piece_masks[(index + 6) % 12] = mask.flip_vertical();
+        }
+        flipped.refresh_hash();
+
+        flipped
+    }
+    /// Same position with files mirrored, only possible once neither side can castle
+    pub fn mirrored(&self) -> Option<Self> {
+        if self.castle_rights != CastlePermissions::NONE {
+            return None;
+        }
+        let mut mirrored = *self;
+        mirrored.enpassant_square = self.enpassant_square.map(Square::mirror_horizontal);
+        for (offset, &piece) in self.squares.iter().enumerate() {
+            mirrored.squares[Square(offset as u8).mirror_horizontal().offset() as usize] = piece;
+        }
+        for (mirrored_mask, &mask) in mirrored.piece_masks.iter_mut().zip(self.piece_masks.iter()) {
+            *mirrored_mask = mask.mirror_horizontal();
+        }
+        mirrored.refresh_hash();
+
+        Some(mirrored)
+    }
+
+    /// Recompute the stored hash and sub keys after editing the public fields directly
+    #[inline]
+    pub fn refresh_hash(&mut self) {
+        self.hash = self.compute_zobrist_hash();
+        self.sub_keys = self.compute_sub_keys();
+    }
+
     pub fn mut_piece_mask(&mut self, piece: ColoredPiece) -> &mut u64 {
         match piece {
             ColoredPiece::WPawn => self.piece_masks[ColoredPiece::WPawn as usize].borrow_mut(),
@@ -84,16 +274,23 @@ impl Position {
 
 #[cfg(not(debug_assertions))]
 impl Position {
-    fn print_bitboard(&self, bb: u64) {}
+    pub fn print_bitboard(_bb: u64) {}
 }
 
 #[cfg(debug_assertions)]
 impl Position {
     pub fn print_bitboard(bb: u64) {
-        let binary_board = format!("{:0064b}", bb);
-        for i in 0..8 {
-            println!("{}", &binary_board[i*8..(i + 1)*8]);
-        }
+        println!("{}", BitboardDiagram(bb));
+    }
+}
+
+/// FEN of the standard starting position
+pub const START_FEN: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+
+impl Position {
+    /// Standard starting position
+    pub fn start() -> Self {
+        Position::from_fen(START_FEN, FenMode::Strict).expect("starting position fen is valid")
     }
 }
 
@@ -108,70 +305,265 @@ impl Default for Position {
             enpassant_square: None,
             piece_masks: [0; 12],
             squares: [ColoredPiece::None; 64],
+            hash: 0,
+            sub_keys: SubKeys::default(),
         }
     }
 }
 
-/// Parse FEN string into position
-impl TryFrom<String> for Position {
-    type Error = errors::FenParseError;
-    fn try_from(fen: String) -> Result<Self, Self::Error> {
-        let side: Side;
-        let fullmove_count: u16;
-        let halfmove_clock: Option<u8>;
-        let castle_rights: CastlePermissions;
-        let enpassant_square: Option<Square>;
-        let mut piece_masks = [0; 12];
-        let mut squares = [ColoredPiece::None; 64];
-
-        let fen_error = errors::InvalidFenString(fen.clone());
-        let mut fen_chunks = fen.split(' ');
-        let board_chunk = fen_chunks.next().ok_or(errors::FenParseError::InvalidFenString(fen_error.clone()))?;
-
-        let side_chunk = fen_chunks.next().ok_or(errors::FenParseError::InvalidFenString(fen_error.clone()))?;
-        side = Side::try_from(String::from(side_chunk)).or_else(|e| Err(errors::FenParseError::InvalidSideError(e, String::from(side_chunk))))?;
-
-        let castle_chunk = fen_chunks.next().ok_or(errors::FenParseError::InvalidFenString(fen_error.clone()))?;
-        castle_rights = CastlePermissions::try_from(String::from(castle_chunk)).or_else(|e| Err(errors::FenParseError::InvalidCastlesError(e, String::from(castle_chunk))))?;
-
-        let enpassant_chunk = fen_chunks.next().ok_or(errors::InvalidFenString(String::from("expected en passant square or -")))?;
-        enpassant_square = Square::try_from(String::from(enpassant_chunk)).ok();
-
-        let halfmove_chunk = fen_chunks.next().ok_or(errors::InvalidFenString(String::from("expected half move clock or -")))?;
-        halfmove_clock = String::from(halfmove_chunk).parse::<u8>().ok();
-
-        let fullmove_chunk = fen_chunks.next().ok_or(errors::InvalidFenString(String::from("expected full move count or -")))?;
-        fullmove_count = String::from(fullmove_chunk).parse::<u16>().unwrap_or(1);
-
-        let mut total_offset: u8 = 56;
-        for board_char in board_chunk.chars() {
-            if board_char.is_digit(10) {
-                total_offset += board_char.to_digit(10).unwrap() as u8;
-            } else if board_char == '/' {
-                total_offset -= 16u8;
-            } else {
-                let piece = ColoredPiece::from(board_char);
-                let square = Square(total_offset);
-                piece_masks[piece as usize] |= square.mask();
-                squares[total_offset as usize] = piece;
-                total_offset += 1;
-            }
+/// How forgiving FEN parsing is
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum FenMode {
+    /// Exactly 6 fields separated by single spaces where every field has to be well formed
+    Strict,
+    /// Tolerate quirks common in FENs found in the wild
+    ///
+    /// Surrounding or repeated whitespace, missing or `-` move counters, a zero full move count,
+    /// castles out of order, long or uppercase side names and en passant squares on the wrong rank (ignored)
+    Lenient,
+}
+
+impl Position {
+    /// Parse a FEN string reporting the field and character position of the first problem found
+    pub fn from_fen(fen: &str, mode: FenMode) -> Result<Self, errors::FenParseError> {
+        use errors::{FenErrorKind, FenField, FenParseError};
+
+        let fields = split_fen_fields(fen, mode)?;
+        let field_count = fields.len();
+        let required_fields = if mode == FenMode::Strict { 6 } else { 4 };
+        if field_count < required_fields {
+            return Err(FenParseError::new(FenField::ALL[field_count], fen.chars().count(), FenErrorKind::MissingField));
+        }
+        if field_count > 6 {
+            return Err(FenParseError::new(FenField::FullmoveCount, fields[6].0, FenErrorKind::TrailingCharacters));
         }
 
-        Ok(Position {
+        let (board_position, board_chunk) = fields[0];
+        let (squares, piece_masks) = parse_fen_board(board_position, board_chunk, mode)?;
+
+        let (side_position, side_chunk) = fields[1];
+        let side = match (side_chunk, mode) {
+            ("w", _) => Side::White,
+            ("b", _) => Side::Black,
+            (_, FenMode::Lenient) => Side::try_from(String::from(side_chunk))
+                .map_err(|_| FenParseError::new(FenField::Side, side_position, FenErrorKind::InvalidSide))?,
+            (_, FenMode::Strict) => return Err(FenParseError::new(FenField::Side, side_position, FenErrorKind::InvalidSide)),
+        };
+
+        let (castle_position, castle_chunk) = fields[2];
+        let castle_rights = parse_fen_castles(castle_position, castle_chunk, mode)?;
+
+        let (enpassant_position, enpassant_chunk) = fields[3];
+        let enpassant_square = if enpassant_chunk == "-" {
+            None
+        } else {
+            let square = enpassant_chunk.parse::<Square>()
+                .map_err(|e| FenParseError::new(FenField::EnPassant, enpassant_position, FenErrorKind::InvalidSquare(e)))?;
+            // The en passant square is behind the pawn that just moved, so it depends on who moved last
+            let expected_rank = if side == Side::White { Rank::Sixth } else { Rank::Third };
+            match mode {
+                _ if square.rank() == expected_rank => Some(square),
+                FenMode::Strict => return Err(FenParseError::new(FenField::EnPassant, enpassant_position, FenErrorKind::EnPassantRank(square.to_string()))),
+                FenMode::Lenient => None,
+            }
+        };
+
+        let halfmove_clock = match fields.get(4) {
+            Some(&(halfmove_position, halfmove_chunk)) => parse_fen_number::<u8>(FenField::HalfmoveClock, halfmove_position, halfmove_chunk, mode)?.unwrap_or(0),
+            None => 0,
+        };
+
+        let fullmove_count = match fields.get(5) {
+            Some(&(fullmove_position, fullmove_chunk)) => match parse_fen_number::<u16>(FenField::FullmoveCount, fullmove_position, fullmove_chunk, mode)? {
+                Some(0) if mode == FenMode::Strict => return Err(FenParseError::new(FenField::FullmoveCount, fullmove_position, FenErrorKind::NumberOutOfRange)),
+                Some(0) | None => 1,
+                Some(fullmove_count) => fullmove_count,
+            },
+            None => 1,
+        };
+
+        let mut position = Position {
             side,
             fullmove_count,
-            halfmove_clock,
+            halfmove_clock: Some(halfmove_clock),
             castle_rights,
             enpassant_square,
             piece_masks,
             squares,
-        })
+            hash: 0,
+            sub_keys: SubKeys::default(),
+        };
+        position.refresh_hash();
+
+        Ok(position)
+    }
+}
+
+/// Split a FEN string into its fields paired with the character position each field starts at
+fn split_fen_fields(fen: &str, mode: FenMode) -> Result<Vec<(usize, &str)>, errors::FenParseError> {
+    use errors::{FenErrorKind, FenField, FenParseError};
+
+    let mut fields: Vec<(usize, &str)> = Vec::with_capacity(6);
+    // Character position and byte offset of the field being read
+    let mut field_start: Option<(usize, usize)> = None;
+    let mut position = 0;
+    for (byte_offset, c) in fen.char_indices() {
+        if c.is_whitespace() {
+            if let Some((start_position, start_offset)) = field_start.take() {
+                fields.push((start_position, &fen[start_offset..byte_offset]));
+                // Only a single space is allowed between fields
+                if mode == FenMode::Strict && c == ' ' {
+                    position += 1;
+                    continue;
+                }
+            }
+            if mode == FenMode::Strict {
+                let field = FenField::ALL[fields.len().min(5)];
+                return Err(FenParseError::new(field, position, FenErrorKind::UnexpectedWhitespace));
+            }
+        } else if field_start.is_none() {
+            field_start = Some((position, byte_offset));
+        }
+        position += 1;
+    }
+    match field_start {
+        Some((start_position, start_offset)) => fields.push((start_position, &fen[start_offset..])),
+        // Strict FENs can not end with the separating space
+        None if mode == FenMode::Strict && !fields.is_empty() => {
+            let field = FenField::ALL[fields.len().min(5)];
+            return Err(FenParseError::new(field, position - 1, FenErrorKind::UnexpectedWhitespace));
+        },
+        None => {},
+    }
+
+    Ok(fields)
+}
+
+/// Parse the piece placement field of a FEN string into the squares list and piece masks
+fn parse_fen_board(board_position: usize, board_chunk: &str, mode: FenMode) -> Result<([ColoredPiece; 64], [u64; 12]), errors::FenParseError> {
+    use errors::{FenErrorKind, FenField, FenParseError};
+
+    let mut piece_masks = [0; 12];
+    let mut squares = [ColoredPiece::None; 64];
+    // Ranks are listed from the eighth down to the first
+    let mut y = 7u8;
+    let mut x = 0u8;
+    let mut previous_digit = false;
+    for (index, board_char) in board_chunk.chars().enumerate() {
+        let position = board_position + index;
+        let error = |kind| FenParseError::new(FenField::Board, position, kind);
+        if let Some(empty_squares) = board_char.to_digit(10).filter(|digit| (1..=8).contains(digit)) {
+            if previous_digit && mode == FenMode::Strict {
+                return Err(error(FenErrorKind::ConsecutiveDigits));
+            }
+            x += empty_squares as u8;
+            if x > 8 {
+                return Err(error(FenErrorKind::TooManySquares(RANK_CHARS[y as usize])));
+            }
+            previous_digit = true;
+            continue;
+        }
+        previous_digit = false;
+        if board_char == '/' {
+            if x < 8 {
+                return Err(error(FenErrorKind::TooFewSquares(RANK_CHARS[y as usize])));
+            }
+            if y == 0 {
+                return Err(error(FenErrorKind::TooManyRanks));
+            }
+            y -= 1;
+            x = 0;
+        } else {
+            let piece = ColoredPiece::from(board_char);
+            if piece == ColoredPiece::None {
+                return Err(error(FenErrorKind::InvalidPiece(board_char)));
+            }
+            if x >= 8 {
+                return Err(error(FenErrorKind::TooManySquares(RANK_CHARS[y as usize])));
+            }
+            let square = Square(y * 8 + x);
+            piece_masks[piece as usize] |= square.mask();
+            squares[square.offset() as usize] = piece;
+            x += 1;
+        }
+    }
+    let end_position = board_position + board_chunk.chars().count();
+    if y > 0 {
+        return Err(FenParseError::new(FenField::Board, end_position, FenErrorKind::TooFewRanks));
+    }
+    if x < 8 {
+        return Err(FenParseError::new(FenField::Board, end_position, FenErrorKind::TooFewSquares(RANK_CHARS[0])));
+    }
+
+    Ok((squares, piece_masks))
+}
+
+/// Parse the castles field of a FEN string, strict mode requires the KQkq order without repeats
+fn parse_fen_castles(castle_position: usize, castle_chunk: &str, mode: FenMode) -> Result<CastlePermissions, errors::FenParseError> {
+    use errors::{FenErrorKind, FenField, FenParseError};
+
+    if castle_chunk == "-" {
+        return Ok(CastlePermissions::NONE);
+    }
+    let mut castle_rights = CastlePermissions::NONE;
+    for (index, castle_char) in castle_chunk.chars().enumerate() {
+        let error = |kind| FenParseError::new(FenField::Castles, castle_position + index, kind);
+        let castle = match castle_char {
+            'K' => CastlePermissions::WHITE_KING,
+            'Q' => CastlePermissions::WHITE_QUEEN,
+            'k' => CastlePermissions::BLACK_KING,
+            'q' => CastlePermissions::BLACK_QUEEN,
+            _ => return Err(error(FenErrorKind::InvalidCastles(castle_char))),
+        };
+        // The flags are ordered KQkq so any castle at or below the highest seen is out of order or repeated
+        if mode == FenMode::Strict && castle.bits() <= castle_rights.bits() {
+            return Err(error(FenErrorKind::CastlesOrder(castle_char)));
+        }
+        castle_rights |= castle;
+    }
+
+    Ok(castle_rights)
+}
+
+/// Parse a move counter field of a FEN string, lenient mode allows `-` (parsed to none)
+fn parse_fen_number<T: FromStr>(field: errors::FenField, position: usize, chunk: &str, mode: FenMode) -> Result<Option<T>, errors::FenParseError> {
+    use errors::{FenErrorKind, FenParseError};
+
+    if mode == FenMode::Lenient && chunk == "-" {
+        return Ok(None);
+    }
+    if let Some(index) = chunk.chars().position(|c| !c.is_ascii_digit()) {
+        return Err(FenParseError::new(field, position + index, FenErrorKind::InvalidNumber));
+    }
+    chunk.parse::<T>()
+        .map(Some)
+        .map_err(|_| FenParseError::new(field, position, FenErrorKind::NumberOutOfRange))
+}
+
+/// Parse a strict FEN string into a position
+impl FromStr for Position {
+    type Err = errors::FenParseError;
+    fn from_str(fen: &str) -> Result<Self, Self::Err> {
+        Position::from_fen(fen, FenMode::Strict)
+    }
+}
+
+/// Parse a strict FEN string into a position
+impl TryFrom<String> for Position {
+    type Error = errors::FenParseError;
+    fn try_from(fen: String) -> Result<Self, Self::Error> {
+        Position::from_fen(&fen, FenMode::Strict)
     }
 }
 
 impl From<Position> for String {
     fn from(pos: Position) -> Self {
+        String::from(&pos)
+    }
+}
+
+impl From<&Position> for String {
+    fn from(pos: &Position) -> Self {
         // 84 is longest possible FEN
         // 64 for each possible square
         // 6 for the slashes between
@@ -192,8 +584,7 @@ impl From<Position> for String {
 
         for y in (0..8usize).rev() {
             let mut current_blanks = 0u32;
-            for x in 0..8usize {
-                let piece = squares_mapped[y][x];
+            for (x, &piece) in squares_mapped[y].iter().enumerate() {
                 if piece == ColoredPiece::None {
                     if x == 7 {
                         builder.push(char::from_digit(current_blanks + 1, 10).unwrap());
@@ -229,8 +620,8 @@ impl From<Position> for String {
         builder.push(' ');
 
         // En passant square
-        if pos.enpassant_square.is_some() {
-            builder.push_str(pos.enpassant_square.unwrap().to_string().as_str());
+        if let Some(enpassant_square) = pos.enpassant_square {
+            builder.push_str(enpassant_square.to_string().as_str());
         } else {
             builder.push('-');
         }
@@ -239,8 +630,8 @@ impl From<Position> for String {
         builder.push(' ');
 
         // Half move clock
-        if pos.halfmove_clock.is_some() {
-            builder.push_str(pos.halfmove_clock.unwrap().to_string().as_str());
+        if let Some(halfmove_clock) = pos.halfmove_clock {
+            builder.push_str(halfmove_clock.to_string().as_str());
         } else {
             builder.push('0');
         }
@@ -331,4 +722,244 @@ mod tests {
             assert_eq!(output_fen, fen.clone(), "Output FEN did not match input FEN\nExpected: '{}'\nFound:    '{}'", fen, output_fen);
         }
     }
+
+    fn fen_error(fen: &str, mode: FenMode) -> errors::FenParseError {
+        match Position::from_fen(fen, mode) {
+            Ok(_) => panic!("Expected FEN '{}' to fail parsing", fen),
+            Err(error) => error,
+        }
+    }
+
+    #[test]
+    fn strict_fen_rejects_invalid_pieces() {
+        let error = fen_error("rnbqkbnr/ppppxppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1", FenMode::Strict);
+        assert_eq!(error.field, errors::FenField::Board);
+        assert_eq!(error.position, 13);
+        assert!(matches!(error.kind, errors::FenErrorKind::InvalidPiece('x')));
+    }
+
+    #[test]
+    fn strict_fen_rejects_bad_rank_lengths() {
+        let error = fen_error("rnbqkbnr/pppppppp/9/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1", FenMode::Strict);
+        assert!(matches!(error.kind, errors::FenErrorKind::InvalidPiece('9')));
+        let error = fen_error("rnbqkbnrr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1", FenMode::Strict);
+        assert_eq!(error.position, 8);
+        assert!(matches!(error.kind, errors::FenErrorKind::TooManySquares('8')));
+        let error = fen_error("rnbqkbnr/pppppppp/8/8/44p/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1", FenMode::Lenient);
+        assert_eq!(error.position, 24);
+        assert!(matches!(error.kind, errors::FenErrorKind::TooManySquares('4')));
+        let error = fen_error("rnbqkbnr/pppppppp/8/8/7/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1", FenMode::Strict);
+        assert_eq!(error.position, 23);
+        assert!(matches!(error.kind, errors::FenErrorKind::TooFewSquares('4')));
+        let error = fen_error("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP w KQkq - 0 1", FenMode::Strict);
+        assert_eq!(error.position, 34);
+        assert!(matches!(error.kind, errors::FenErrorKind::TooFewRanks));
+        let error = fen_error("rnbqkbnr/pppppppp/8/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1", FenMode::Strict);
+        assert_eq!(error.position, 36);
+        assert!(matches!(error.kind, errors::FenErrorKind::TooManyRanks));
+        let error = fen_error("rnbqkbnr/pppppppp/8/8/44/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1", FenMode::Strict);
+        assert_eq!(error.position, 23);
+        assert!(matches!(error.kind, errors::FenErrorKind::ConsecutiveDigits));
+        assert!(Position::from_fen("rnbqkbnr/pppppppp/8/8/44/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1", FenMode::Lenient).is_ok());
+    }
+
+    #[test]
+    fn strict_fen_rejects_bad_fields() {
+        let error = fen_error("8/8/8/8/8/8/8/8 x - - 0 1", FenMode::Strict);
+        assert_eq!(error.field, errors::FenField::Side);
+        assert_eq!(error.position, 16);
+        let error = fen_error("8/8/8/8/8/8/8/8 w KQxq - 0 1", FenMode::Strict);
+        assert_eq!(error.field, errors::FenField::Castles);
+        assert_eq!(error.position, 20);
+        assert!(matches!(error.kind, errors::FenErrorKind::InvalidCastles('x')));
+        let error = fen_error("8/8/8/8/8/8/8/8 w QK - 0 1", FenMode::Strict);
+        assert_eq!(error.position, 19);
+        assert!(matches!(error.kind, errors::FenErrorKind::CastlesOrder('K')));
+        let error = fen_error("8/8/8/8/8/8/8/8 w KK - 0 1", FenMode::Strict);
+        assert!(matches!(error.kind, errors::FenErrorKind::CastlesOrder('K')));
+        let error = fen_error("8/8/8/8/8/8/8/8 w - e9 0 1", FenMode::Strict);
+        assert_eq!(error.field, errors::FenField::EnPassant);
+        assert_eq!(error.position, 20);
+        assert!(matches!(error.kind, errors::FenErrorKind::InvalidSquare(_)));
+        let error = fen_error("8/8/8/8/8/8/8/8 w - e3 0 1", FenMode::Strict);
+        assert!(matches!(error.kind, errors::FenErrorKind::EnPassantRank(_)));
+        let error = fen_error("8/8/8/8/8/8/8/8 w - - 1x 1", FenMode::Strict);
+        assert_eq!(error.field, errors::FenField::HalfmoveClock);
+        assert_eq!(error.position, 23);
+        assert!(matches!(error.kind, errors::FenErrorKind::InvalidNumber));
+        let error = fen_error("8/8/8/8/8/8/8/8 w - - 256 1", FenMode::Strict);
+        assert!(matches!(error.kind, errors::FenErrorKind::NumberOutOfRange));
+        let error = fen_error("8/8/8/8/8/8/8/8 w - - 0 0", FenMode::Strict);
+        assert_eq!(error.field, errors::FenField::FullmoveCount);
+        assert!(matches!(error.kind, errors::FenErrorKind::NumberOutOfRange));
+    }
+
+    #[test]
+    fn strict_fen_rejects_bad_whitespace() {
+        let error = fen_error("8/8/8/8/8/8/8/8 w - -  0 1", FenMode::Strict);
+        assert_eq!(error.field, errors::FenField::HalfmoveClock);
+        assert_eq!(error.position, 22);
+        assert!(matches!(error.kind, errors::FenErrorKind::UnexpectedWhitespace));
+        let error = fen_error(" 8/8/8/8/8/8/8/8 w - - 0 1", FenMode::Strict);
+        assert_eq!(error.position, 0);
+        let error = fen_error("8/8/8/8/8/8/8/8 w - - 0 1 ", FenMode::Strict);
+        assert_eq!(error.position, 25);
+        let error = fen_error("8/8/8/8/8/8/8/8\tw - - 0 1", FenMode::Strict);
+        assert_eq!(error.position, 15);
+        let error = fen_error("8/8/8/8/8/8/8/8 w - - 0 1 extra", FenMode::Strict);
+        assert_eq!(error.position, 26);
+        assert!(matches!(error.kind, errors::FenErrorKind::TrailingCharacters));
+    }
+
+    #[test]
+    fn strict_fen_requires_every_field() {
+        let error = fen_error("8/8/8/8/8/8/8/8 w - -", FenMode::Strict);
+        assert_eq!(error.field, errors::FenField::HalfmoveClock);
+        assert_eq!(error.position, 21);
+        assert!(matches!(error.kind, errors::FenErrorKind::MissingField));
+        let error = fen_error("8/8/8/8/8/8/8/8 w", FenMode::Lenient);
+        assert_eq!(error.field, errors::FenField::Castles);
+    }
+
+    #[test]
+    fn lenient_fen_accepts_quirks() {
+        let expected = "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1";
+        let quirky_fens = [
+            "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3",
+            "  rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR   b KQkq  e3 0 1 \n",
+            "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b qkQK e3 - -",
+            "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR black KQkq e3 0 0",
+            "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0",
+        ];
+        for fen in &quirky_fens {
+            let position = Position::from_fen(fen, FenMode::Lenient);
+            assert!(position.is_ok(), "Failed to leniently parse '{}' {:?}", fen, position.err());
+            assert_eq!(String::from(position.unwrap()), expected);
+            assert!(Position::from_fen(fen, FenMode::Strict).is_err(), "Strict parsing accepted '{}'", fen);
+        }
+        // En passant squares on the wrong rank are dropped
+        let position = Position::from_fen("8/8/8/8/8/8/8/8 w - e3 0 1", FenMode::Lenient).unwrap();
+        assert_eq!(position.enpassant_square, None);
+    }
+
+    #[test]
+    fn color_flipped_works() {
+        let position = Position::from_str("r3k2r/pp3ppp/8/3pP3/8/8/PPP2PPP/R3K1NR w KQk d6 0 12").unwrap();
+        let flipped = position.color_flipped();
+        assert_eq!(String::from(&flipped), "r3k1nr/ppp2ppp/8/8/3Pp3/8/PP3PPP/R3K2R b Kkq d3 0 12");
+        assert_eq!(flipped.white_mask(), position.black_mask().flip_vertical());
+        assert_eq!(flipped.piece_mask(ColoredPiece::BKnight), position.piece_mask(ColoredPiece::WKnight).flip_vertical());
+        assert_eq!(String::from(flipped.color_flipped()), String::from(position));
+    }
+
+    #[test]
+    fn position_is_copy() {
+        fn assert_copy<T: Copy>() {}
+        assert_copy::<Position>();
+    }
+
+    #[test]
+    fn mirrored_works() {
+        let position = Position::from_str("rnbqkbnr/pp1ppppp/8/2p5/4P3/5N2/PPPP1PPP/RNBQKB1R b KQkq - 1 2").unwrap();
+        assert!(position.mirrored().is_none(), "Mirrored a position with castle rights");
+
+        let position = Position::from_str("8/5k2/8/2pP4/8/8/1K6/8 w - c6 0 40").unwrap();
+        let mirrored = position.mirrored().unwrap();
+        assert_eq!(String::from(&mirrored), "8/2k5/8/4Pp2/8/8/6K1/8 w - f6 0 40");
+        assert_eq!(mirrored.piece_mask(ColoredPiece::WPawn), position.piece_mask(ColoredPiece::WPawn).mirror_horizontal());
+        assert_eq!(String::from(mirrored.mirrored().unwrap()), String::from(position));
+    }
+
+    #[test]
+    fn from_str_works() {
+        let position: Position = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1".parse().unwrap();
+        assert_eq!(position.occupied_mask(), 0xffff00000000ffff);
+        assert!("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq -".parse::<Position>().is_err());
+    }
+
+    fn validation_problems(fen: &str) -> Vec<errors::PositionError> {
+        let position = Position::from_fen(fen, FenMode::Strict).unwrap();
+        position.validate().err().unwrap_or_default()
+    }
+
+    #[test]
+    fn attacked_mask_works() {
+        let position = Position::try_from("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1".to_string()).unwrap();
+        assert_eq!(position.attacked_mask(Side::White), masks::RANK_3 | (masks::RANK_2 | masks::RANK_1) & !(named::A1.mask() | named::H1.mask()));
+        assert!(position.is_square_attacked(named::F3, Side::White));
+        assert!(!position.is_square_attacked(named::E4, Side::White));
+        assert!(position.is_square_attacked(named::F6, Side::Black));
+        assert!(!position.in_check(Side::White));
+        let position = Position::try_from("rnb1kbnr/pppp1ppp/8/4p3/6Pq/5P2/PPPPP2P/RNBQKBNR w KQkq - 1 3".to_string()).unwrap();
+        assert!(position.in_check(Side::White));
+        assert!(!position.in_check(Side::Black));
+        assert_eq!(position.king_square(Side::White), Some(named::E1));
+    }
+
+    #[test]
+    fn validate_accepts_legal_positions() {
+        let fens = [
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1",
+            "rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3",
+            "4k3/8/8/8/8/8/8/4K3 w - - 0 1",
+            "r3k2r/8/8/8/8/8/8/R3K2R b KQkq - 0 1",
+            "NNNNN3/NNNNN3/8/8/8/8/8/K6k w - - 0 1",
+        ];
+        for fen in &fens {
+            assert_eq!(validation_problems(fen), vec![], "Expected '{}' to be valid", fen);
+        }
+    }
+
+    #[test]
+    fn validate_finds_king_problems() {
+        assert_eq!(validation_problems("8/8/8/8/8/8/8/4K3 w - - 0 1"), vec![errors::PositionError::MissingKing(Side::Black)]);
+        assert_eq!(validation_problems("3kk3/8/8/8/8/8/8/4K3 w - - 0 1"), vec![errors::PositionError::TooManyKings(Side::Black, 2)]);
+        assert_eq!(validation_problems("4k3/4R3/8/8/8/8/8/4K3 w - - 0 1"), vec![errors::PositionError::OpponentInCheck(Side::Black)]);
+        assert_eq!(validation_problems("4k3/4R3/8/8/8/8/8/4K3 b - - 0 1"), vec![]);
+    }
+
+    #[test]
+    fn validate_finds_piece_count_problems() {
+        assert_eq!(validation_problems("P3k3/8/8/8/8/8/8/4K2p w - - 0 1"), vec![
+            errors::PositionError::PawnOnBackRank(named::H1),
+            errors::PositionError::PawnOnBackRank(named::A8),
+        ]);
+        assert_eq!(validation_problems("4k3/8/8/8/8/P7/PPPPPPPP/4K3 w - - 0 1"), vec![errors::PositionError::TooManyPawns(Side::White, 9)]);
+        assert_eq!(validation_problems("4k3/8/8/8/8/8/PPPPPPPP/QQ2K3 w - - 0 1"), vec![errors::PositionError::TooManyPromotedPieces(Side::White)]);
+        assert_eq!(validation_problems("4k3/8/8/8/8/NNNNNNNN/PPPPPPPP/QQ2K3 w - - 0 1"), vec![
+            errors::PositionError::TooManyPieces(Side::White, 19),
+            errors::PositionError::TooManyPromotedPieces(Side::White),
+        ]);
+    }
+
+    #[test]
+    fn validate_finds_castle_problems() {
+        assert_eq!(validation_problems("r3k3/8/8/8/8/8/8/4K2R w KQkq - 0 1"), vec![
+            errors::PositionError::InvalidCastleRights(CastlePermissions::WHITE_QUEEN | CastlePermissions::BLACK_KING),
+        ]);
+        assert_eq!(validation_problems("r3k2r/8/8/8/8/8/8/R4K1R w KQkq - 0 1"), vec![
+            errors::PositionError::InvalidCastleRights(CastlePermissions::WHITE_ALL),
+        ]);
+    }
+
+    #[test]
+    fn validate_finds_en_passant_problems() {
+        // No pawn that could have just jumped
+        assert_eq!(validation_problems("4k3/8/8/8/8/8/4P3/4K3 b - e3 0 1"), vec![errors::PositionError::InvalidEnPassant(named::E3)]);
+        // The jumping pawn could not have come from an occupied square
+        assert_eq!(validation_problems("4k3/8/8/8/4P3/8/4N3/4K3 b - e3 0 1"), vec![errors::PositionError::InvalidEnPassant(named::E3)]);
+        assert_eq!(validation_problems("4k3/8/8/3p4/8/8/8/4K3 w - d6 0 1"), vec![]);
+    }
+
+    #[test]
+    fn validate_finds_inconsistent_squares() {
+        let mut position = Position::try_from("4k3/8/8/8/8/8/8/4K3 w - - 0 1".to_string()).unwrap();
+        position.squares[named::D4.offset() as usize] = ColoredPiece::WQueen;
+        *position.mut_piece_mask(ColoredPiece::BRook) |= named::E1.mask();
+        assert_eq!(position.validate(), Err(vec![
+            errors::PositionError::InconsistentSquare(named::E1),
+            errors::PositionError::InconsistentSquare(named::D4),
+        ]));
+    }
 }
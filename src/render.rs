@@ -0,0 +1,208 @@
+use std::fmt;
+
+use super::pieces::{ColoredPiece, PieceRepr};
+use super::position::Position;
+use super::side::Side;
+use super::square::{Square, FILE_CHARS, RANK_CHARS};
+use super::zobrist::ZobristHashable;
+
+/// Characters used to draw pieces and the board frame
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Charset {
+    /// Piece letters (white uppercase) inside a `+-|` frame
+    Ascii,
+    /// Chess symbols inside a box drawing frame
+    Unicode,
+}
+
+/// Frame pieces ordered top (left, middle, right), between ranks, bottom, then horizontal and vertical lines
+struct Frame {
+    top: [char; 3],
+    middle: [char; 3],
+    bottom: [char; 3],
+    horizontal: char,
+    vertical: char,
+}
+
+const ASCII_FRAME: Frame = Frame {
+    top: ['+', '+', '+'],
+    middle: ['+', '+', '+'],
+    bottom: ['+', '+', '+'],
+    horizontal: '-',
+    vertical: '|',
+};
+
+const UNICODE_FRAME: Frame = Frame {
+    top: ['\u{250C}', '\u{252C}', '\u{2510}'],
+    middle: ['\u{251C}', '\u{253C}', '\u{2524}'],
+    bottom: ['\u{2514}', '\u{2534}', '\u{2518}'],
+    horizontal: '\u{2500}',
+    vertical: '\u{2502}',
+};
+
+/// How a position is drawn as text
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct RenderOptions {
+    /// Characters for the pieces and frame
+    pub charset: Charset,
+    /// Side shown at the bottom of the board
+    pub orientation: Side,
+    /// Show rank numbers and file letters around the board
+    pub coordinates: bool,
+    /// From and to squares of the last move, drawn in brackets
+    pub last_move: Option<(Square, Square)>,
+    /// Show the FEN, side to move and hash under the board
+    pub footer: bool,
+}
+
+impl Default for RenderOptions {
+    fn default() -> Self {
+        RenderOptions {
+            charset: Charset::Ascii,
+            orientation: Side::White,
+            coordinates: true,
+            last_move: None,
+            footer: false,
+        }
+    }
+}
+
+impl Position {
+    /// Draw the board as text
+    pub fn render(&self, options: &RenderOptions) -> String {
+        let frame = match options.charset {
+            Charset::Ascii => &ASCII_FRAME,
+            Charset::Unicode => &UNICODE_FRAME,
+        };
+        let margin = if options.coordinates { "  " } else { "" };
+        let border = |[left, middle, right]: [char; 3]| {
+            let cell: String = std::iter::repeat(frame.horizontal).take(3).collect();
+            format!("{}{}{}{}\n", margin, left, vec![cell; 8].join(&middle.to_string()), right)
+        };
+        // Board coordinates in drawing order (top to bottom, left to right)
+        let (rows, columns): (Vec<u8>, Vec<u8>) = match options.orientation {
+            Side::White => ((0..8).rev().collect(), (0..8).collect()),
+            Side::Black => ((0..8).collect(), (0..8).rev().collect()),
+        };
+        let highlighted = |square: Square| options.last_move.map_or(false, |(from, to)| square == from || square == to);
+
+        let mut board = border(frame.top);
+        for (row_index, &y) in rows.iter().enumerate() {
+            if options.coordinates {
+                board.push(RANK_CHARS[y as usize]);
+                board.push(' ');
+            }
+            board.push(frame.vertical);
+            for &x in &columns {
+                let square = Square(y * 8 + x);
+                let piece = self.squares[square.offset() as usize];
+                let symbol = match (piece, options.charset) {
+                    (ColoredPiece::None, _) => ' ',
+                    (_, Charset::Ascii) => piece.to_ascii(),
+                    (_, Charset::Unicode) => piece.to_unicode(),
+                };
+                let (left, right) = if highlighted(square) { ('[', ']') } else { (' ', ' ') };
+                board.push(left);
+                board.push(symbol);
+                board.push(right);
+                board.push(frame.vertical);
+            }
+            board.push('\n');
+            board.push_str(&border(if row_index == 7 { frame.bottom } else { frame.middle }));
+        }
+        if options.coordinates {
+            board.push_str(margin);
+            for &x in &columns {
+                board.push_str("  ");
+                board.push(FILE_CHARS[x as usize]);
+                board.push(' ');
+            }
+            board.push('\n');
+        }
+        if options.footer {
+            board.push_str(&format!("Fen: {}\n", String::from(self)));
+            board.push_str(&format!("Side to move: {}\n", self.side));
+            board.push_str(&format!("Key: {:016x}\n", self.zobrist_hash()));
+        }
+
+        board
+    }
+}
+
+/// Draw the board with the default render options
+impl fmt::Display for Position {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.render(&RenderOptions::default()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::square::named;
+    use std::convert::TryFrom;
+
+    const START: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+
+    #[test]
+    fn display_draws_ascii_board() {
+        let position = Position::try_from(START.to_string()).unwrap();
+        let expected = concat!(
+            "  +---+---+---+---+---+---+---+---+\n",
+            "8 | r | n | b | q | k | b | n | r |\n",
+            "  +---+---+---+---+---+---+---+---+\n",
+            "7 | p | p | p | p | p | p | p | p |\n",
+            "  +---+---+---+---+---+---+---+---+\n",
+            "6 |   |   |   |   |   |   |   |   |\n",
+            "  +---+---+---+---+---+---+---+---+\n",
+            "5 |   |   |   |   |   |   |   |   |\n",
+            "  +---+---+---+---+---+---+---+---+\n",
+            "4 |   |   |   |   |   |   |   |   |\n",
+            "  +---+---+---+---+---+---+---+---+\n",
+            "3 |   |   |   |   |   |   |   |   |\n",
+            "  +---+---+---+---+---+---+---+---+\n",
+            "2 | P | P | P | P | P | P | P | P |\n",
+            "  +---+---+---+---+---+---+---+---+\n",
+            "1 | R | N | B | Q | K | B | N | R |\n",
+            "  +---+---+---+---+---+---+---+---+\n",
+            "    a   b   c   d   e   f   g   h \n",
+        );
+        assert_eq!(position.to_string(), expected);
+    }
+
+    #[test]
+    fn render_flips_and_highlights() {
+        let position = Position::try_from("4k3/8/8/8/8/8/4P3/4K3 b - - 0 1".to_string()).unwrap();
+        let options = RenderOptions {
+            orientation: Side::Black,
+            coordinates: false,
+            last_move: Some((named::E1, named::E2)),
+            ..RenderOptions::default()
+        };
+        let rendered = position.render(&options);
+        let lines: Vec<&str> = rendered.lines().collect();
+        assert_eq!(lines.len(), 17);
+        // First rank is at the top with the h-file on the left
+        assert_eq!(lines[1], "|   |   |   |[K]|   |   |   |   |");
+        assert_eq!(lines[3], "|   |   |   |[P]|   |   |   |   |");
+        assert_eq!(lines[15], "|   |   |   | k |   |   |   |   |");
+    }
+
+    #[test]
+    fn render_unicode_and_footer() {
+        let position = Position::try_from(START.to_string()).unwrap();
+        let options = RenderOptions {
+            charset: Charset::Unicode,
+            footer: true,
+            ..RenderOptions::default()
+        };
+        let rendered = position.render(&options);
+        let lines: Vec<&str> = rendered.lines().collect();
+        assert_eq!(lines[0], "  \u{250C}\u{2500}\u{2500}\u{2500}\u{252C}\u{2500}\u{2500}\u{2500}\u{252C}\u{2500}\u{2500}\u{2500}\u{252C}\u{2500}\u{2500}\u{2500}\u{252C}\u{2500}\u{2500}\u{2500}\u{252C}\u{2500}\u{2500}\u{2500}\u{252C}\u{2500}\u{2500}\u{2500}\u{252C}\u{2500}\u{2500}\u{2500}\u{2510}");
+        assert!(lines[1].contains('\u{265A}'));
+        assert!(lines[15].contains('\u{2654}'));
+        assert_eq!(lines[18], format!("Fen: {}", START));
+        assert_eq!(lines[19], "Side to move: white");
+        assert_eq!(lines[20], format!("Key: {:016x}", position.zobrist_hash()));
+    }
+}
@@ -0,0 +1,208 @@
+use std::convert::TryFrom;
+use super::errors::SanError;
+use super::board::MakeUnmakeBoard;
+use super::chess_move::Move;
+use super::pieces::{Piece, PieceRepr};
+use super::position::Position;
+use super::side::Side;
+use super::castles::CastlePermissions;
+use super::square::{mask_to_square_iter, named, File, Rank, Square};
+use super::transposition::PackedMove;
+
+/// Piece named by an uppercase SAN letter
+fn san_piece(c: char) -> Option<Piece> {
+    match c {
+        'N' => Some(Piece::Knight),
+        'B' => Some(Piece::Bishop),
+        'R' => Some(Piece::Rook),
+        'Q' => Some(Piece::Queen),
+        'K' => Some(Piece::King),
+        _ => None,
+    }
+}
+
+impl Position {
+    /// SAN string for a legal move, with the fewest disambiguating characters and a check or mate mark
+    pub fn san(&self, m: &Move) -> String {
+        let mut san = if m.castles_used.intersects(CastlePermissions::BOTH_KINGS) {
+            "O-O".to_string()
+        } else if !m.castles_used.is_empty() {
+            "O-O-O".to_string()
+        } else {
+            let mut san = String::new();
+            if m.piece == Piece::Pawn {
+                if m.captured_piece.is_some() {
+                    san.push_str(&m.from.file().to_string());
+                }
+            } else {
+                san.push(m.piece.to_ascii().to_ascii_uppercase());
+                let others: Vec<Square> = mask_to_square_iter(self.piece_mask(m.piece.color(self.side)) & !m.from.mask())
+                    .filter(|&from| {
+                        self.unpack_move(PackedMove::new(from, m.to, None))
+                            .map_or(false, |other| other.castles_used.is_empty() && self.is_legal(&other))
+                    })
+                    .collect();
+                if !others.is_empty() {
+                    if others.iter().all(|other| other.file() != m.from.file()) {
+                        san.push_str(&m.from.file().to_string());
+                    } else if others.iter().all(|other| other.rank() != m.from.rank()) {
+                        san.push_str(&m.from.rank().to_string());
+                    } else {
+                        san.push_str(&m.from.to_string());
+                    }
+                }
+            }
+            if m.captured_piece.is_some() {
+                san.push('x');
+            }
+            san.push_str(&m.to.to_string());
+            if let Some(promotion) = m.promoted_piece {
+                san.push('=');
+                san.push(promotion.to_ascii().to_ascii_uppercase());
+            }
+            san
+        };
+
+        let mut after = *self;
+        after.make_move(m);
+        if after.in_check(after.side) {
+            san.push(if after.has_legal_moves() { '+' } else { '#' });
+        }
+
+        san
+    }
+
+    /// Legal move for a SAN string like `Nbd7`, `exd8=Q+` or `O-O`, tolerating trailing check and annotation marks
+    pub fn parse_san(&self, san: &str) -> Result<Move, SanError> {
+        let invalid = || SanError::Invalid(san.to_string());
+        let trimmed = san.trim_end_matches(['+', '#', '!', '?']);
+
+        let castle_to = match trimmed {
+            "O-O" | "0-0" => Some(if self.side == Side::White { named::G1 } else { named::G8 }),
+            "O-O-O" | "0-0-0" => Some(if self.side == Side::White { named::C1 } else { named::C8 }),
+            _ => None,
+        };
+        if let Some(to) = castle_to {
+            let from = if self.side == Side::White { named::E1 } else { named::E8 };
+            return match self.unpack_move(PackedMove::new(from, to, None)) {
+                Some(m) if !m.castles_used.is_empty() && self.is_legal(&m) => Ok(m),
+                _ => Err(SanError::Illegal(san.to_string())),
+            };
+        }
+
+        let mut chars: Vec<char> = trimmed.chars().filter(|&c| c != 'x' && c != ':').collect();
+        let piece = match chars.first().and_then(|&c| san_piece(c)) {
+            Some(piece) => {
+                chars.remove(0);
+                piece
+            },
+            None => Piece::Pawn,
+        };
+        // Promotions are written `e8=Q` and sometimes `e8Q`
+        let promotion = match chars.last().and_then(|&c| san_piece(c)) {
+            Some(promotion) if piece == Piece::Pawn && promotion != Piece::King => {
+                chars.pop();
+                if chars.last() == Some(&'=') {
+                    chars.pop();
+                }
+                Some(promotion)
+            },
+            _ => None,
+        };
+        if chars.len() < 2 || chars.len() > 4 {
+            return Err(invalid());
+        }
+
+        let destination: String = chars[chars.len() - 2..].iter().collect();
+        let to = destination.parse::<Square>().map_err(|_| invalid())?;
+        let mut from_mask = self.piece_mask(piece.color(self.side));
+        for &c in chars[..chars.len() - 2].iter() {
+            from_mask &= match (File::try_from(c), Rank::try_from(c)) {
+                (Ok(file), _) => file.mask(),
+                (_, Ok(rank)) => rank.mask(),
+                _ => return Err(invalid()),
+            };
+        }
+
+        let mut candidates = mask_to_square_iter(from_mask)
+            .filter_map(|from| self.unpack_move(PackedMove::new(from, to, promotion)))
+            .filter(|m| m.castles_used.is_empty() && self.is_legal(m));
+        match (candidates.next(), candidates.next()) {
+            (Some(m), None) => Ok(m),
+            (Some(_), Some(_)) => Err(SanError::Ambiguous(san.to_string())),
+            (None, _) => Err(SanError::Illegal(san.to_string())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chess_move::UCIMove;
+    use std::str::FromStr;
+
+    fn uci(fen: &str, san: &str) -> Result<String, SanError> {
+        Position::from_str(fen).unwrap().parse_san(san).map(|m| m.to_uci())
+    }
+
+    #[test]
+    fn parse_san_works() {
+        let start = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+        assert_eq!(uci(start, "e4").unwrap(), "e2e4");
+        assert_eq!(uci(start, "Nf3").unwrap(), "g1f3");
+        assert_eq!(uci(start, "Nf3!?").unwrap(), "g1f3");
+        assert_eq!(uci(start, "e5"), Err(SanError::Illegal("e5".to_string())));
+        assert_eq!(uci(start, "Zz9"), Err(SanError::Invalid("Zz9".to_string())));
+
+        let kiwipete = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1";
+        assert_eq!(uci(kiwipete, "O-O").unwrap(), "e1g1");
+        assert_eq!(uci(kiwipete, "0-0-0").unwrap(), "e1c1");
+        assert_eq!(uci(kiwipete, "dxe6").unwrap(), "d5e6");
+        assert_eq!(uci(kiwipete, "Bxa6").unwrap(), "e2a6");
+        assert_eq!(uci(kiwipete, "Qxh3").unwrap(), "f3h3");
+    }
+
+    #[test]
+    fn parse_san_disambiguates() {
+        let rooks = "4k3/8/8/8/8/8/4K3/R6R w - - 0 1";
+        assert_eq!(uci(rooks, "Rf1"), Err(SanError::Ambiguous("Rf1".to_string())));
+        assert_eq!(uci(rooks, "Rhf1").unwrap(), "h1f1");
+        assert_eq!(uci(rooks, "Rbf1"), Err(SanError::Illegal("Rbf1".to_string())));
+        assert_eq!(uci(rooks, "Rad1").unwrap(), "a1d1");
+
+        let knights = "4k3/8/8/1N6/8/1N6/8/4K3 w - - 0 1";
+        assert_eq!(uci(knights, "N5d4").unwrap(), "b5d4");
+        assert_eq!(uci(knights, "Nb3d4").unwrap(), "b3d4");
+
+        // A pinned piece doesn't need disambiguating
+        let pinned = "4k3/4r3/8/8/8/2N1N3/8/4K3 w - - 0 1";
+        assert_eq!(uci(pinned, "Nd5").unwrap(), "c3d5");
+    }
+
+    #[test]
+    fn san_round_trips() {
+        let cases = [
+            ("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1", vec!["O-O", "O-O-O", "dxe6", "Bxa6", "Qxh3", "Nxf7", "Nc6", "a4"]),
+            ("4k3/8/8/8/8/8/4K3/R6R w - - 0 1", vec!["Rhf1", "Rad1", "Ra8+"]),
+            ("4k3/8/8/1N6/8/1N3N2/8/4K3 w - - 0 1", vec!["N5d4", "Nb3d4", "Nfd4", "Nbd2", "Nc7+"]),
+            ("1n2k3/P7/8/8/8/8/8/4K3 w - - 0 1", vec!["a8=Q", "axb8=N", "axb8=Q+"]),
+            ("rnbqkbnr/pppp1ppp/8/4p3/6P1/5P2/PPPPP2P/RNBQKBNR b KQkq - 0 2", vec!["Qh4#"]),
+        ];
+        for (fen, moves) in cases.iter() {
+            let position = Position::from_str(fen).unwrap();
+            for &san in moves.iter() {
+                assert_eq!(position.san(&position.parse_san(san).unwrap()), san);
+            }
+        }
+    }
+
+    #[test]
+    fn parse_san_promotions() {
+        let fen = "1n2k3/P7/8/8/8/8/8/4K3 w - - 0 1";
+        assert_eq!(uci(fen, "a8=Q").unwrap(), "a7a8q");
+        assert_eq!(uci(fen, "axb8=N+").unwrap(), "a7b8n");
+        assert_eq!(uci(fen, "a8R").unwrap(), "a7a8r");
+        assert!(uci(fen, "a8").is_err());
+        assert!(uci(fen, "a8=K").is_err());
+    }
+}
@@ -1,11 +1,12 @@
 
 use std::convert::TryFrom;
+use std::str::FromStr;
 
 use super::errors;
 use std::collections::VecDeque;
 use std::fmt::Formatter;
 use core::fmt;
-use std::ops::{Add, Sub};
+use super::side::Side;
 
 pub const FILE_CHARS: [char; 8] = ['a', 'b', 'c', 'd', 'e', 'f', 'g', 'h'];
 pub const RANK_CHARS: [char; 8] = ['1', '2', '3', '4', '5', '6', '7', '8'];
@@ -29,6 +30,12 @@ pub mod masks {
     pub const RANK_6: u64 = 0x0000FF0000000000;
     pub const RANK_7: u64 = 0x00FF000000000000;
     pub const RANK_8: u64 = 0xFF00000000000000;
+    // Diagonals
+    pub const A1_H8_DIAGONAL: u64 = 0x8040201008040201;
+    pub const A8_H1_DIAGONAL: u64 = 0x0102040810204080;
+    // Square colors
+    pub const DARK_SQUARES: u64 = 0xAA55AA55AA55AA55;
+    pub const LIGHT_SQUARES: u64 = !DARK_SQUARES;
 
     pub const ALL: u64 = 0xFFFFFFFFFFFFFFFF;
     pub const NONE: u64 = 0x0;
@@ -142,7 +149,7 @@ pub mod named {
 /// Convert a unsigned 64 bit mask to an iterator over each set (1 at that offset) square
 pub fn mask_to_square_iter(mask: u64) -> impl Iterator<Item=Square> {
     let mut squares: VecDeque<Square> = VecDeque::with_capacity(mask.count_ones() as usize);
-    let mut mutable_mask = mask.clone();
+    let mut mutable_mask = mask;
 
     while mutable_mask > 0 {
         // Get index of first 1
@@ -230,11 +237,158 @@ pub mod old {
     }
 }*/
 
-#[derive(Copy, Clone, Debug, Eq, PartialEq)]
-pub struct Square(pub u8);
+/// Column of the board
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub enum File {
+    A,
+    B,
+    C,
+    D,
+    E,
+    F,
+    G,
+    H,
+}
+
+impl File {
+    pub const ALL: [File; 8] = [File::A, File::B, File::C, File::D, File::E, File::F, File::G, File::H];
+
+    /// Index of the file (a-file is 0)
+    #[inline]
+    pub fn x(self) -> u8 {
+        self as u8
+    }
+    /// Mask of every square on the file
+    #[inline]
+    pub fn mask(self) -> u64 {
+        masks::A_FILE << self.x()
+    }
+}
+
+impl TryFrom<u8> for File {
+    type Error = errors::FileIndexError;
+    fn try_from(x: u8) -> Result<Self, Self::Error> {
+        File::ALL.get(x as usize).copied().ok_or(errors::FileIndexError(x))
+    }
+}
+
+impl TryFrom<char> for File {
+    type Error = errors::SquareParseError;
+    fn try_from(file: char) -> Result<Self, Self::Error> {
+        let x = FILE_CHARS.iter().position(|&c| c == file).ok_or(errors::SquareParseError::SquareFileError(file))?;
+        Ok(File::ALL[x])
+    }
+}
+
+impl fmt::Display for File {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", FILE_CHARS[self.x() as usize])
+    }
+}
+
+/// Row of the board
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub enum Rank {
+    First,
+    Second,
+    Third,
+    Fourth,
+    Fifth,
+    Sixth,
+    Seventh,
+    Eighth,
+}
+
+impl Rank {
+    pub const ALL: [Rank; 8] = [Rank::First, Rank::Second, Rank::Third, Rank::Fourth, Rank::Fifth, Rank::Sixth, Rank::Seventh, Rank::Eighth];
+
+    /// Index of the rank (first rank is 0)
+    #[inline]
+    pub fn y(self) -> u8 {
+        self as u8
+    }
+    /// Rank as seen from the other side of the board (first rank becomes eighth)
+    #[inline]
+    pub fn flip(self) -> Self {
+        Rank::ALL[7 - self.y() as usize]
+    }
+    /// Mask of every square on the rank
+    #[inline]
+    pub fn mask(self) -> u64 {
+        masks::RANK_1 << (self.y() * 8)
+    }
+}
+
+impl TryFrom<u8> for Rank {
+    type Error = errors::RankIndexError;
+    fn try_from(y: u8) -> Result<Self, Self::Error> {
+        Rank::ALL.get(y as usize).copied().ok_or(errors::RankIndexError(y))
+    }
+}
+
+impl TryFrom<char> for Rank {
+    type Error = errors::SquareParseError;
+    fn try_from(rank: char) -> Result<Self, Self::Error> {
+        let y = RANK_CHARS.iter().position(|&c| c == rank).ok_or(errors::SquareParseError::SquareRankError(rank))?;
+        Ok(Rank::ALL[y])
+    }
+}
+
+impl fmt::Display for Rank {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", RANK_CHARS[self.y() as usize])
+    }
+}
+
+/// Shade of a square on the board (a1 is dark)
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub enum SquareColor {
+    Light,
+    Dark,
+}
+
+const fn distance_table(manhattan: bool) -> [[u8; 64]; 64] {
+    let mut table = [[0u8; 64]; 64];
+    let mut a = 0usize;
+    while a < 64 {
+        let mut b = 0usize;
+        while b < 64 {
+            let (ax, ay, bx, by) = (a % 8, a / 8, b % 8, b / 8);
+            let x_distance = ax.abs_diff(bx);
+            let y_distance = ay.abs_diff(by);
+            table[a][b] = if manhattan {
+                (x_distance + y_distance) as u8
+            } else if x_distance > y_distance {
+                x_distance as u8
+            } else {
+                y_distance as u8
+            };
+            b += 1;
+        }
+        a += 1;
+    }
+
+    table
+}
+
+/// King move distance between two square offsets (maximum of the file and rank distance)
+pub static CHEBYSHEV_DISTANCE: [[u8; 64]; 64] = distance_table(false);
+/// Rook move distance between two square offsets ignoring blockers (sum of the file and rank distance)
+pub static MANHATTAN_DISTANCE: [[u8; 64]; 64] = distance_table(true);
+
+/// A square on the board, always a valid offset (0 for a1 through 63 for h8)
+///
+/// Outside of this crate squares are created with [`named`], [`Square::from_file_rank`], `TryFrom<u8>` or by parsing
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub struct Square(pub(crate) u8);
 
 impl Square {
-    #[deprecated(note = "Square is now just a trait on the offset itself, offset is no longer needed")]
+    /// Square at the intersection of a file and rank
+    #[inline]
+    pub fn from_file_rank(file: File, rank: Rank) -> Self {
+        Square(rank.y() * 8 + file.x())
+    }
+    /// Offset of the square from a1 (0-63)
     #[inline]
     pub fn offset(self) -> u8 {
         let Square(offset) = self;
@@ -255,50 +409,148 @@ impl Square {
         let Square(offset) = self;
         1u64 << offset as u64
     }
+    /// Typed file of the square
+    #[inline]
+    pub fn file(self) -> File {
+        File::ALL[self.x() as usize]
+    }
+    /// Typed rank of the square
+    #[inline]
+    pub fn rank(self) -> Rank {
+        Rank::ALL[self.y() as usize]
+    }
+    /// Rank as seen by a side (e2 is on the second rank for white and the seventh for black)
+    #[inline]
+    pub fn relative_rank(self, side: Side) -> Rank {
+        match side {
+            Side::White => self.rank(),
+            Side::Black => self.rank().flip(),
+        }
+    }
+    /// Whether the square is light or dark
+    #[inline]
+    pub fn color(self) -> SquareColor {
+        if (self.x() + self.y()) & 1 == 0 { SquareColor::Dark } else { SquareColor::Light }
+    }
+    /// Square as seen from the other side of the board (e2 becomes e7)
+    #[inline]
+    pub fn flip(self) -> Self {
+        self.flip_vertical()
+    }
+    /// King move distance to another square
+    #[inline]
+    pub fn distance(self, other: Square) -> u8 {
+        let (Square(from), Square(to)) = (self, other);
+        CHEBYSHEV_DISTANCE[from as usize][to as usize]
+    }
+    /// Rook move distance to another square ignoring blockers
+    #[inline]
+    pub fn manhattan_distance(self, other: Square) -> u8 {
+        let (Square(from), Square(to)) = (self, other);
+        MANHATTAN_DISTANCE[from as usize][to as usize]
+    }
+    /// Square a number of files (east is positive) and ranks (north is positive) away, none if that leaves the board
+    #[inline]
+    pub fn translate(self, file_delta: i8, rank_delta: i8) -> Option<Self> {
+        // Widened so extreme deltas can't overflow
+        let x = self.x() as i16 + file_delta as i16;
+        let y = self.y() as i16 + rank_delta as i16;
+        if (0..8).contains(&x) && (0..8).contains(&y) {
+            Some(Square((y * 8 + x) as u8))
+        } else {
+            None
+        }
+    }
+    /// Add to the offset, none if that leaves the board
+    #[inline]
+    pub fn checked_add(self, rhs: u8) -> Option<Self> {
+        let Square(offset) = self;
+        offset.checked_add(rhs).filter(|&offset| offset < 64).map(Square)
+    }
+    /// Subtract from the offset, none if that leaves the board
+    #[inline]
+    pub fn checked_sub(self, rhs: u8) -> Option<Self> {
+        let Square(offset) = self;
+        offset.checked_sub(rhs).map(Square)
+    }
+    /// Flip rank (a1 becomes a8)
+    #[inline]
+    pub fn flip_vertical(self) -> Self {
+        let Square(offset) = self;
+        Square(offset ^ 56)
+    }
+    /// Mirror file (a1 becomes h1)
+    #[inline]
+    pub fn mirror_horizontal(self) -> Self {
+        let Square(offset) = self;
+        Square(offset ^ 7)
+    }
+    /// Flip about the a1-h8 diagonal (b1 becomes a2)
+    #[inline]
+    pub fn flip_diagonal(self) -> Self {
+        Square(self.x() * 8 + self.y())
+    }
+    /// Flip about the a8-h1 diagonal (a1 becomes h8)
+    #[inline]
+    pub fn flip_anti_diagonal(self) -> Self {
+        Square((7 - self.x()) * 8 + (7 - self.y()))
+    }
+    /// Rotate a quarter turn clockwise (a1 becomes a8)
+    #[inline]
+    pub fn rotate_90_clockwise(self) -> Self {
+        Square((7 - self.x()) * 8 + self.y())
+    }
+    /// Rotate a quarter turn anti clockwise (a1 becomes h1)
+    #[inline]
+    pub fn rotate_90_anti_clockwise(self) -> Self {
+        Square(self.x() * 8 + (7 - self.y()))
+    }
+    /// Rotate a half turn (a1 becomes h8)
+    #[inline]
+    pub fn rotate_180(self) -> Self {
+        let Square(offset) = self;
+        Square(offset ^ 63)
+    }
 }
 impl fmt::Display for Square {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
-        let Square(offset) = self.clone();
-        if offset > 63 {
-            panic!("Attempting to convert invalid move into string");
+        write!(f, "{}{}", self.file(), self.rank())
+    }
+}
+impl TryFrom<u8> for Square {
+    type Error = errors::SquareOffsetError;
+    fn try_from(offset: u8) -> Result<Self, Self::Error> {
+        if offset < 64 {
+            Ok(Square(offset))
+        } else {
+            Err(errors::SquareOffsetError(offset))
         }
-        write!(f, "{}{}", FILE_CHARS[self.x() as usize], RANK_CHARS[self.y() as usize])
     }
 }
-impl TryFrom<String> for Square {
-    type Error = errors::SquareParseError;
-    fn try_from(file_rank: String) -> Result<Self, Self::Error> {
-        let file_string_copy = file_rank.clone();
-        let mut file_rank_chars = file_string_copy.chars();
-        let err = errors::SquareParseError::SquareLengthError(file_rank.clone());
-        let file = file_rank_chars.next().ok_or(err.clone())?;
-        let rank = file_rank_chars.next().ok_or(err.clone())?;
+impl From<Square> for u8 {
+    fn from(square: Square) -> Self {
+        square.offset()
+    }
+}
+impl FromStr for Square {
+    type Err = errors::SquareParseError;
+    fn from_str(file_rank: &str) -> Result<Self, Self::Err> {
+        let mut file_rank_chars = file_rank.chars();
+        let length_error = || errors::SquareParseError::SquareLengthError(file_rank.to_string());
+        let file = file_rank_chars.next().ok_or_else(length_error)?;
+        let rank = file_rank_chars.next().ok_or_else(length_error)?;
         // If we took 2 chars but there is more left error
         if file_rank_chars.next().is_some() {
-            return Err(err.clone());
+            return Err(length_error());
         }
-        let file_index = FILE_CHARS.iter().position(|&c| c == file).ok_or(errors::SquareParseError::SquareFileError(file))? as u8;
-        let rank_index = RANK_CHARS.iter().position(|&c| c == rank).ok_or(errors::SquareParseError::SquareRankError(rank))? as u8;
-
-        Ok(Square(rank_index * 8 + file_index))
-    }
-}
-impl Add<u8> for Square {
-    type Output = Square;
 
-    #[inline]
-    fn add(self, rhs: u8) -> Self::Output {
-        let Square(offset) = self;
-        Square(offset + rhs)
+        Ok(Square::from_file_rank(File::try_from(file)?, Rank::try_from(rank)?))
     }
 }
-impl Sub<u8> for Square {
-    type Output = Square;
-
-    #[inline]
-    fn sub(self, rhs: u8) -> Self::Output {
-        let Square(offset) = self;
-        Square(offset - rhs)
+impl TryFrom<String> for Square {
+    type Error = errors::SquareParseError;
+    fn try_from(file_rank: String) -> Result<Self, Self::Error> {
+        file_rank.parse()
     }
 }
 #[cfg(test)]
@@ -372,9 +624,50 @@ mod tests {
     }
 
     #[test]
-    #[should_panic]
-    fn offboard_to_string_panics() {
-        Square(64).to_string();
+    fn try_from_u8_works() {
+        assert_eq!(Square::try_from(0u8).unwrap(), named::A1);
+        assert_eq!(Square::try_from(28u8).unwrap(), named::E4);
+        assert_eq!(Square::try_from(63u8).unwrap(), named::H8);
+        assert!(Square::try_from(64u8).is_err());
+        assert!(Square::try_from(255u8).is_err());
+    }
+
+    #[test]
+    fn from_file_rank_works() {
+        assert_eq!(Square::from_file_rank(File::A, Rank::First), named::A1);
+        assert_eq!(Square::from_file_rank(File::E, Rank::Fourth), named::E4);
+        assert_eq!(Square::from_file_rank(File::H, Rank::Eighth), named::H8);
+        for offset in 0..64 {
+            let square = Square(offset);
+            assert_eq!(Square::from_file_rank(square.file(), square.rank()), square);
+        }
+    }
+
+    #[test]
+    fn file_rank_masks_work() {
+        assert_eq!(File::A.mask(), masks::A_FILE);
+        assert_eq!(File::D.mask(), masks::D_FILE);
+        assert_eq!(File::H.mask(), masks::H_FILE);
+        assert_eq!(Rank::First.mask(), masks::RANK_1);
+        assert_eq!(Rank::Fifth.mask(), masks::RANK_5);
+        assert_eq!(Rank::Eighth.mask(), masks::RANK_8);
+        assert_eq!(File::try_from(3u8).unwrap(), File::D);
+        assert!(File::try_from(8u8).is_err());
+        assert_eq!(Rank::try_from(7u8).unwrap(), Rank::Eighth);
+        assert!(Rank::try_from(8u8).is_err());
+    }
+
+    #[test]
+    fn from_str_works() {
+        assert_eq!("a1".parse::<Square>().unwrap(), named::A1);
+        assert_eq!("e4".parse::<Square>().unwrap(), named::E4);
+        assert_eq!("h8".parse::<Square>().unwrap(), named::H8);
+        assert_eq!(Square::try_from("c6".to_string()).unwrap(), named::C6);
+        assert!(matches!("e".parse::<Square>(), Err(errors::SquareParseError::SquareLengthError(_))));
+        assert!(matches!("e44".parse::<Square>(), Err(errors::SquareParseError::SquareLengthError(_))));
+        assert!(matches!("i4".parse::<Square>(), Err(errors::SquareParseError::SquareFileError('i'))));
+        assert!(matches!("e9".parse::<Square>(), Err(errors::SquareParseError::SquareRankError('9'))));
+        assert!(matches!("E4".parse::<Square>(), Err(errors::SquareParseError::SquareFileError('E'))));
     }
 
     #[test]
@@ -388,5 +681,130 @@ mod tests {
         assert_eq!(masks::file_for_x(6), masks::G_FILE);
         assert_eq!(masks::file_for_x(7), masks::H_FILE);
     }
-    // TODO: Test TryFrom<String>
+
+    #[test]
+    fn flip_vertical_works() {
+        assert_eq!(named::A1.flip_vertical(), named::A8);
+        assert_eq!(named::E2.flip_vertical(), named::E7);
+        assert_eq!(named::H5.flip_vertical(), named::H4);
+    }
+
+    #[test]
+    fn mirror_horizontal_works() {
+        assert_eq!(named::A1.mirror_horizontal(), named::H1);
+        assert_eq!(named::E2.mirror_horizontal(), named::D2);
+        assert_eq!(named::C7.mirror_horizontal(), named::F7);
+    }
+
+    #[test]
+    fn flip_diagonal_works() {
+        assert_eq!(named::A1.flip_diagonal(), named::A1);
+        assert_eq!(named::B1.flip_diagonal(), named::A2);
+        assert_eq!(named::H1.flip_diagonal(), named::A8);
+        assert_eq!(named::E4.flip_diagonal(), named::D5);
+    }
+
+    #[test]
+    fn flip_anti_diagonal_works() {
+        assert_eq!(named::A1.flip_anti_diagonal(), named::H8);
+        assert_eq!(named::H1.flip_anti_diagonal(), named::H1);
+        assert_eq!(named::A8.flip_anti_diagonal(), named::A8);
+        assert_eq!(named::B1.flip_anti_diagonal(), named::H7);
+    }
+
+    #[test]
+    fn rotations_work() {
+        assert_eq!(named::A1.rotate_90_clockwise(), named::A8);
+        assert_eq!(named::A8.rotate_90_clockwise(), named::H8);
+        assert_eq!(named::B1.rotate_90_clockwise(), named::A7);
+        assert_eq!(named::A1.rotate_90_anti_clockwise(), named::H1);
+        assert_eq!(named::B1.rotate_90_anti_clockwise(), named::H2);
+        assert_eq!(named::A1.rotate_180(), named::H8);
+        assert_eq!(named::E2.rotate_180(), named::D7);
+        for offset in 0..64 {
+            let square = Square(offset);
+            assert_eq!(square.rotate_90_clockwise().rotate_90_anti_clockwise(), square);
+            assert_eq!(square.rotate_90_clockwise().rotate_90_clockwise(), square.rotate_180());
+        }
+    }
+
+    #[test]
+    fn file_works() {
+        assert_eq!(named::A1.file(), File::A);
+        assert_eq!(named::E4.file(), File::E);
+        assert_eq!(named::H8.file(), File::H);
+        assert_eq!(File::C.to_string(), "c");
+    }
+
+    #[test]
+    fn rank_works() {
+        assert_eq!(named::A1.rank(), Rank::First);
+        assert_eq!(named::E4.rank(), Rank::Fourth);
+        assert_eq!(named::H8.rank(), Rank::Eighth);
+        assert_eq!(Rank::Sixth.to_string(), "6");
+    }
+
+    #[test]
+    fn relative_rank_works() {
+        assert_eq!(named::E2.relative_rank(Side::White), Rank::Second);
+        assert_eq!(named::E2.relative_rank(Side::Black), Rank::Seventh);
+        assert_eq!(named::A8.relative_rank(Side::Black), Rank::First);
+        assert_eq!(named::D5.relative_rank(Side::Black), Rank::Fourth);
+    }
+
+    #[test]
+    fn color_works() {
+        assert_eq!(named::A1.color(), SquareColor::Dark);
+        assert_eq!(named::H1.color(), SquareColor::Light);
+        assert_eq!(named::D1.color(), SquareColor::Light);
+        assert_eq!(named::E1.color(), SquareColor::Dark);
+        assert_eq!(named::H8.color(), SquareColor::Dark);
+        assert_eq!(named::A8.color(), SquareColor::Light);
+    }
+
+    #[test]
+    fn flip_works() {
+        assert_eq!(named::E2.flip(), named::E7);
+        assert_eq!(named::A8.flip(), named::A1);
+    }
+
+    #[test]
+    fn distance_works() {
+        assert_eq!(named::A1.distance(named::A1), 0);
+        assert_eq!(named::A1.distance(named::H8), 7);
+        assert_eq!(named::A1.distance(named::B3), 2);
+        assert_eq!(named::E4.distance(named::D5), 1);
+        assert_eq!(named::G2.distance(named::B4), 5);
+    }
+
+    #[test]
+    fn manhattan_distance_works() {
+        assert_eq!(named::A1.manhattan_distance(named::A1), 0);
+        assert_eq!(named::A1.manhattan_distance(named::H8), 14);
+        assert_eq!(named::A1.manhattan_distance(named::B3), 3);
+        assert_eq!(named::E4.manhattan_distance(named::D5), 2);
+        assert_eq!(named::G2.manhattan_distance(named::B4), 7);
+    }
+
+    #[test]
+    fn translate_works() {
+        assert_eq!(named::E4.translate(1, 2), Some(named::F6));
+        assert_eq!(named::E4.translate(-4, -3), Some(named::A1));
+        assert_eq!(named::A1.translate(-1, 0), None);
+        assert_eq!(named::H4.translate(1, 0), None);
+        assert_eq!(named::B7.translate(0, 2), None);
+        assert_eq!(named::H1.translate(i8::MAX, 0), None);
+        assert_eq!(named::A8.translate(i8::MIN, i8::MAX), None);
+        assert_eq!(named::H8.translate(i8::MAX, i8::MAX), None);
+        assert_eq!(named::A1.translate(i8::MIN, i8::MIN), None);
+    }
+
+    #[test]
+    fn checked_arithmetic_works() {
+        assert_eq!(named::E2.checked_add(16), Some(named::E4));
+        assert_eq!(named::E7.checked_sub(16), Some(named::E5));
+        assert_eq!(named::H8.checked_add(1), None);
+        assert_eq!(named::A1.checked_sub(1), None);
+        assert_eq!(named::A1.checked_add(255), None);
+    }
 }
\ No newline at end of file
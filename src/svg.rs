@@ -0,0 +1,284 @@
+use std::fmt::Write;
+
+use super::pieces::{ColoredPiece, Piece};
+use super::position::Position;
+use super::side::Side;
+use super::square::{Square, SquareColor, FILE_CHARS, RANK_CHARS};
+
+const LIGHT_SQUARE: &str = "#f0d9b5";
+const DARK_SQUARE: &str = "#b58863";
+const HIGHLIGHT: &str = "#9bc700";
+const ARROW: &str = "#15781b";
+
+/// Width and height of the coordinate space piece shapes are drawn in
+const PIECE_VIEWBOX: f32 = 45.0;
+
+/// Outline drawn for each piece inside a 45x45 box
+fn piece_shape(piece: Piece) -> &'static str {
+    match piece {
+        Piece::Pawn => concat!(
+            r#"<circle cx="22.5" cy="14" r="5"/>"#,
+            r#"<path d="M15 36 L30 36 L26 20 L19 20 Z"/>"#,
+        ),
+        Piece::Knight => r#"<path d="M14 36 H33 C33 24 30 14 22 10 L20 7 L18 11 C14 13 11 18 10 22 L13 24 L18 21 C18 26 13 30 14 36 Z"/>"#,
+        Piece::Bishop => concat!(
+            r#"<circle cx="22.5" cy="7" r="2.5"/>"#,
+            r#"<path d="M22.5 9 C17 13 15 19 17 27 H28 C30 19 28 13 22.5 9 Z"/>"#,
+            r#"<path d="M13 36 H32 L29 29 H16 Z"/>"#,
+        ),
+        Piece::Rook => r#"<path d="M12 36 H33 V32 H30 V17 H33 V10 H29 V13 H25 V10 H20 V13 H16 V10 H12 V17 H15 V32 H12 Z"/>"#,
+        Piece::Queen => concat!(
+            r#"<path d="M11 36 H34 L37 14 L29 26 L26 11 L22.5 25 L19 11 L16 26 L8 14 Z"/>"#,
+            r#"<circle cx="8" cy="12" r="2"/><circle cx="19" cy="9" r="2"/><circle cx="26" cy="9" r="2"/><circle cx="37" cy="12" r="2"/>"#,
+        ),
+        Piece::King => concat!(
+            r#"<path d="M21 5 H24 V8 H27 V11 H24 V16 H21 V11 H18 V8 H21 Z"/>"#,
+            r#"<path d="M11 36 H34 L36 22 C32 16 25 18 22.5 22 C20 18 13 16 9 22 Z"/>"#,
+        ),
+        Piece::None => "",
+    }
+}
+
+/// Element id a piece's shape is defined under
+fn piece_id(piece: ColoredPiece) -> String {
+    let kind = match piece.uncolor() {
+        Piece::Pawn => "pawn",
+        Piece::Knight => "knight",
+        Piece::Bishop => "bishop",
+        Piece::Rook => "rook",
+        Piece::Queen => "queen",
+        Piece::King => "king",
+        Piece::None => "none",
+    };
+    let side = if piece.uncolor().color(Side::White) == piece { Side::White } else { Side::Black };
+
+    format!("{}-{}", side, kind)
+}
+
+/// How a position is drawn as an SVG image
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SvgOptions {
+    /// Width and height of a single square in pixels
+    pub square_size: u32,
+    /// Side shown at the bottom of the board
+    pub orientation: Side,
+    /// Label files and ranks along the board edges
+    pub coordinates: bool,
+    /// Squares to tint
+    pub highlights: Vec<Square>,
+    /// Arrows drawn from the first square to the second
+    pub arrows: Vec<(Square, Square)>,
+}
+
+impl Default for SvgOptions {
+    fn default() -> Self {
+        SvgOptions {
+            square_size: 45,
+            orientation: Side::White,
+            coordinates: true,
+            highlights: Vec::new(),
+            arrows: Vec::new(),
+        }
+    }
+}
+
+impl SvgOptions {
+    /// Top left corner of a square in pixels
+    fn origin(&self, square: Square) -> (u32, u32) {
+        let (column, row) = match self.orientation {
+            Side::White => (square.x(), 7 - square.y()),
+            Side::Black => (7 - square.x(), square.y()),
+        };
+
+        (column as u32 * self.square_size, row as u32 * self.square_size)
+    }
+
+    /// Center of a square in pixels
+    fn center(&self, square: Square) -> (f32, f32) {
+        let (x, y) = self.origin(square);
+        let half = self.square_size as f32 / 2.0;
+
+        (x as f32 + half, y as f32 + half)
+    }
+}
+
+impl Position {
+    /// Draw the board as a standalone SVG document
+    pub fn to_svg(&self, options: &SvgOptions) -> String {
+        let size = options.square_size;
+        let board_size = size * 8;
+        let mut svg = String::new();
+        // Writing to a String cannot fail
+        let _ = writeln!(
+            svg,
+            r#"<svg xmlns="http://www.w3.org/2000/svg" xmlns:xlink="http://www.w3.org/1999/xlink" width="{0}" height="{0}" viewBox="0 0 {0} {0}">"#,
+            board_size
+        );
+
+        svg.push_str("<defs>\n");
+        for side in [Side::White, Side::Black] {
+            let (fill, stroke) = match side {
+                Side::White => ("#ffffff", "#000000"),
+                Side::Black => ("#000000", "#ffffff"),
+            };
+            for piece in [Piece::Pawn, Piece::Knight, Piece::Bishop, Piece::Rook, Piece::Queen, Piece::King] {
+                let _ = writeln!(
+                    svg,
+                    r#"<g id="{}" fill="{}" stroke="{}" stroke-width="1.5" stroke-linejoin="round">{}</g>"#,
+                    piece_id(piece.color(side)),
+                    fill,
+                    stroke,
+                    piece_shape(piece)
+                );
+            }
+        }
+        if !options.arrows.is_empty() {
+            let _ = writeln!(
+                svg,
+                r#"<marker id="arrowhead" markerWidth="4" markerHeight="4" refX="2" refY="2" orient="auto"><path d="M0 0 L4 2 L0 4 Z" fill="{}"/></marker>"#,
+                ARROW
+            );
+        }
+        svg.push_str("</defs>\n");
+
+        for offset in 0..64u8 {
+            let square = Square(offset);
+            let (x, y) = options.origin(square);
+            let fill = match square.color() {
+                SquareColor::Dark => DARK_SQUARE,
+                SquareColor::Light => LIGHT_SQUARE,
+            };
+            let _ = writeln!(svg, r#"<rect x="{}" y="{}" width="{2}" height="{2}" fill="{3}"/>"#, x, y, size, fill);
+        }
+
+        for &square in &options.highlights {
+            let (x, y) = options.origin(square);
+            let _ = writeln!(
+                svg,
+                r#"<rect class="highlight" x="{}" y="{}" width="{2}" height="{2}" fill="{3}" fill-opacity="0.5"/>"#,
+                x, y, size, HIGHLIGHT
+            );
+        }
+
+        if options.coordinates {
+            let font_size = size as f32 / 4.0;
+            for column in 0..8u8 {
+                let x = match options.orientation {
+                    Side::White => column,
+                    Side::Black => 7 - column,
+                };
+                let _ = writeln!(
+                    svg,
+                    r#"<text x="{}" y="{}" font-size="{}" font-family="sans-serif">{}</text>"#,
+                    (column as u32 + 1) * size - size / 5,
+                    board_size - size / 16,
+                    font_size,
+                    FILE_CHARS[x as usize]
+                );
+            }
+            for row in 0..8u8 {
+                let y = match options.orientation {
+                    Side::White => 7 - row,
+                    Side::Black => row,
+                };
+                let _ = writeln!(
+                    svg,
+                    r#"<text x="{}" y="{}" font-size="{}" font-family="sans-serif">{}</text>"#,
+                    size / 16,
+                    row as u32 * size + size / 4,
+                    font_size,
+                    RANK_CHARS[y as usize]
+                );
+            }
+        }
+
+        let scale = size as f32 / PIECE_VIEWBOX;
+        for offset in 0..64u8 {
+            let piece = self.squares[offset as usize];
+            if piece == ColoredPiece::None {
+                continue;
+            }
+            let (x, y) = options.origin(Square(offset));
+            let _ = writeln!(
+                svg,
+                r##"<use xlink:href="#{}" transform="translate({} {}) scale({})"/>"##,
+                piece_id(piece),
+                x,
+                y,
+                scale
+            );
+        }
+
+        for &(from, to) in &options.arrows {
+            let (x1, y1) = options.center(from);
+            let (x2, y2) = options.center(to);
+            let _ = writeln!(
+                svg,
+                r#"<line class="arrow" x1="{}" y1="{}" x2="{}" y2="{}" stroke="{}" stroke-width="{}" stroke-opacity="0.8" marker-end="url(#arrowhead)"/>"#,
+                x1,
+                y1,
+                x2,
+                y2,
+                ARROW,
+                size as f32 / 6.0
+            );
+        }
+
+        svg.push_str("</svg>\n");
+
+        svg
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::square::named;
+    use std::convert::TryFrom;
+
+    const START: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+
+    #[test]
+    fn svg_draws_every_piece() {
+        let position = Position::try_from(START.to_string()).unwrap();
+        let svg = position.to_svg(&SvgOptions::default());
+        assert!(svg.starts_with("<svg "));
+        assert!(svg.ends_with("</svg>\n"));
+        assert_eq!(svg.matches("<use ").count(), 32);
+        assert_eq!(svg.matches("<rect ").count(), 64);
+        assert_eq!(svg.matches("<text ").count(), 16);
+        assert!(svg.contains(r##"<use xlink:href="#white-king" transform="translate(180 315) scale(1)"/>"##));
+        assert!(svg.contains(r##"<use xlink:href="#black-queen" transform="translate(135 0) scale(1)"/>"##));
+    }
+
+    #[test]
+    fn svg_flips_board() {
+        let position = Position::try_from(START.to_string()).unwrap();
+        let options = SvgOptions {
+            orientation: Side::Black,
+            coordinates: false,
+            ..SvgOptions::default()
+        };
+        let svg = position.to_svg(&options);
+        assert_eq!(svg.matches("<text ").count(), 0);
+        assert!(svg.contains(r##"<use xlink:href="#white-king" transform="translate(135 0) scale(1)"/>"##));
+        assert!(svg.contains(r##"<use xlink:href="#black-queen" transform="translate(180 315) scale(1)"/>"##));
+    }
+
+    #[test]
+    fn svg_draws_highlights_and_arrows() {
+        let position = Position::try_from(START.to_string()).unwrap();
+        let options = SvgOptions {
+            square_size: 10,
+            highlights: vec![named::E2, named::E4],
+            arrows: vec![(named::E2, named::E4)],
+            ..SvgOptions::default()
+        };
+        let svg = position.to_svg(&options);
+        assert!(svg.contains(r#"width="80" height="80""#));
+        assert_eq!(svg.matches(r#"class="highlight""#).count(), 2);
+        assert!(svg.contains(r#"<rect class="highlight" x="40" y="60" width="10" height="10""#));
+        assert!(svg.contains(r#"<line class="arrow" x1="45" y1="65" x2="45" y2="45""#));
+        assert!(svg.contains(r#"id="arrowhead""#));
+    }
+}
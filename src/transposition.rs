@@ -0,0 +1,914 @@
+use super::chess_move::Move;
+use super::errors::TableFileError;
+use super::pieces::Piece;
+use super::square::Square;
+use super::zobrist::{self, DefaultKeys, ZobristKey, ZobristKeys};
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, AtomicU8, Ordering};
+
+/// Score for delivering mate right now, mates further away score one less per ply
+pub const MATE_SCORE: i16 = 32000;
+/// Deepest ply a mate score can be found at
+pub const MAX_PLY: i16 = 256;
+/// Scores at least this far from zero are mate scores
+pub const MATE_THRESHOLD: i16 = MATE_SCORE - MAX_PLY;
+
+/// Entries sharing an index
+const BUCKET_SIZE: usize = 4;
+/// Bytes taken by a bucket (a key and a data word per entry)
+const BUCKET_BYTES: usize = BUCKET_SIZE * 16;
+/// Ages wrap around after this many searches
+const AGE_CYCLE: u8 = 64;
+
+/// First bytes of a saved table
+const FILE_MAGIC: [u8; 4] = *b"OXTT";
+/// Saved table layout version, bumped whenever the layout or entry packing changes
+const FILE_VERSION: u16 = 1;
+
+/// How a stored score relates to the true score of the position
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub enum Bound {
+    /// Score is exact
+    Exact,
+    /// True score is at least the stored score (fail high)
+    Lower,
+    /// True score is at most the stored score (fail low)
+    Upper,
+}
+
+impl Bound {
+    /// Two bit code, zero is reserved for empty entries
+    #[inline]
+    fn code(self) -> u64 {
+        match self {
+            Bound::Upper => 1,
+            Bound::Lower => 2,
+            Bound::Exact => 3,
+        }
+    }
+    #[inline]
+    fn from_code(code: u64) -> Option<Self> {
+        match code {
+            1 => Some(Bound::Upper),
+            2 => Some(Bound::Lower),
+            3 => Some(Bound::Exact),
+            _ => None,
+        }
+    }
+}
+
+/// Move squeezed into 16 bits: from square, to square and promotion
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Hash)]
+pub struct PackedMove(pub u16);
+
+impl PackedMove {
+    /// No move stored
+    pub const NONE: PackedMove = PackedMove(0);
+
+    /// Pack a move's squares and promotion
+    pub fn new(from: Square, to: Square, promotion: Option<Piece>) -> Self {
+        let promotion_code = match promotion {
+            Some(Piece::Knight) => 1,
+            Some(Piece::Bishop) => 2,
+            Some(Piece::Rook) => 3,
+            Some(Piece::Queen) => 4,
+            _ => 0,
+        };
+
+        PackedMove(from.offset() as u16 | (to.offset() as u16) << 6 | promotion_code << 12)
+    }
+    /// If no move is stored
+    #[inline]
+    pub fn is_none(self) -> bool {
+        self == PackedMove::NONE
+    }
+    /// Square the move starts on
+    #[inline]
+    pub fn from_square(self) -> Square {
+        Square((self.0 & 0x3F) as u8)
+    }
+    /// Square the move ends on
+    #[inline]
+    pub fn to_square(self) -> Square {
+        Square((self.0 >> 6 & 0x3F) as u8)
+    }
+    /// Piece a pawn promotes to
+    #[inline]
+    pub fn promotion(self) -> Option<Piece> {
+        match self.0 >> 12 & 0x7 {
+            1 => Some(Piece::Knight),
+            2 => Some(Piece::Bishop),
+            3 => Some(Piece::Rook),
+            4 => Some(Piece::Queen),
+            _ => None,
+        }
+    }
+    /// If a full move has the same squares and promotion
+    #[inline]
+    pub fn matches(self, m: &Move) -> bool {
+        self == PackedMove::from(m)
+    }
+}
+
+impl From<&Move> for PackedMove {
+    fn from(m: &Move) -> Self {
+        PackedMove::new(m.from, m.to, m.promoted_piece)
+    }
+}
+
+/// Search result stored for a position
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub struct TableEntry {
+    /// Remaining depth the position was searched to
+    pub depth: u8,
+    /// How the score bounds the true score
+    pub bound: Bound,
+    /// Score relative to the probing ply (mate scores count plies from the root)
+    pub score: i16,
+    /// Static evaluation of the position
+    pub eval: i16,
+    /// Best or refutation move found
+    pub best_move: PackedMove,
+    /// Search generation the entry was written in
+    pub age: u8,
+}
+
+impl TableEntry {
+    /// Pack into a single word: move, score, eval, depth, bound then age from the low bits up
+    #[inline]
+    pub(crate) fn pack(&self) -> u64 {
+        self.best_move.0 as u64
+            | (self.score as u16 as u64) << 16
+            | (self.eval as u16 as u64) << 32
+            | (self.depth as u64) << 48
+            | self.bound.code() << 56
+            | ((self.age % AGE_CYCLE) as u64) << 58
+    }
+    /// Unpack a word, empty entries have no bound
+    #[inline]
+    pub(crate) fn unpack(data: u64) -> Option<Self> {
+        Some(TableEntry {
+            best_move: PackedMove(data as u16),
+            score: (data >> 16) as u16 as i16,
+            eval: (data >> 32) as u16 as i16,
+            depth: (data >> 48) as u8,
+            bound: Bound::from_code(data >> 56 & 0x3)?,
+            age: (data >> 58) as u8,
+        })
+    }
+}
+
+/// Convert a root relative score to one relative to the node it is stored at
+#[inline]
+pub fn score_to_table(score: i16, ply: u8) -> i16 {
+    if score >= MATE_THRESHOLD {
+        score + ply as i16
+    } else if score <= -MATE_THRESHOLD {
+        score - ply as i16
+    } else {
+        score
+    }
+}
+
+/// Convert a stored node relative score back to one relative to the root
+#[inline]
+pub fn score_from_table(score: i16, ply: u8) -> i16 {
+    if score >= MATE_THRESHOLD {
+        score - ply as i16
+    } else if score <= -MATE_THRESHOLD {
+        score + ply as i16
+    } else {
+        score
+    }
+}
+
+/// Bits of a key used to pick the bucket and bits stored to verify it
+#[cfg(not(feature = "key128"))]
+#[inline]
+fn split_key(key: ZobristKey) -> (u64, u64) {
+    (key, key)
+}
+
+/// Bits of a key used to pick the bucket and bits stored to verify it
+#[cfg(feature = "key128")]
+#[inline]
+fn split_key(key: ZobristKey) -> (u64, u64) {
+    ((key >> 64) as u64, key as u64)
+}
+
+/// Number of buckets fitting in a size in megabytes (always at least one)
+#[inline]
+fn bucket_count(mb: usize) -> usize {
+    (mb * 1024 * 1024 / BUCKET_BYTES).max(1)
+}
+
+/// Bucket an index key maps to, using the high bits so every table size works
+#[inline]
+fn bucket_index(index_key: u64, bucket_count: usize) -> usize {
+    ((index_key as u128 * bucket_count as u128) >> 64) as usize
+}
+
+/// How many searches ago an entry was written
+#[inline]
+fn relative_age(current: u8, age: u8) -> u8 {
+    (AGE_CYCLE + current - age) % AGE_CYCLE
+}
+
+/// How much an entry is worth keeping, the lowest in a bucket is replaced
+#[inline]
+fn replacement_worth(entry: &TableEntry, current_age: u8) -> i32 {
+    entry.depth as i32 - 8 * relative_age(current_age, entry.age) as i32
+}
+
+#[derive(Copy, Clone, Default)]
+struct Slot {
+    key: u64,
+    data: u64,
+}
+
+/// Slot a key is written to: its own, otherwise an empty one, otherwise the least valuable
+fn replacement_slot(slots: &[Slot; BUCKET_SIZE], check_key: u64, age: u8) -> usize {
+    slots.iter().position(|slot| slot.key == check_key && TableEntry::unpack(slot.data).is_some())
+        .or_else(|| slots.iter().position(|slot| TableEntry::unpack(slot.data).is_none()))
+        .unwrap_or_else(|| {
+            (0..BUCKET_SIZE)
+                .min_by_key(|&index| TableEntry::unpack(slots[index].data).map_or(i32::MIN, |entry| replacement_worth(&entry, age)))
+                .unwrap_or(0)
+        })
+}
+
+/// Move to store, keeping a known move for the same position rather than forgetting it
+#[inline]
+fn kept_move(previous: Slot, check_key: u64, best_move: PackedMove) -> PackedMove {
+    match TableEntry::unpack(previous.data) {
+        Some(entry) if best_move.is_none() && previous.key == check_key => entry.best_move,
+        _ => best_move,
+    }
+}
+
+/// Entry stored for a key with its score adjusted to the probing ply
+#[inline]
+fn find_entry(slots: &[Slot; BUCKET_SIZE], check_key: u64, ply: u8) -> Option<TableEntry> {
+    slots.iter()
+        .filter(|slot| slot.key == check_key)
+        .find_map(|slot| TableEntry::unpack(slot.data))
+        .map(|entry| TableEntry { score: score_from_table(entry.score, ply), ..entry })
+}
+
+/// Permille of sampled slots written during the current search
+fn sampled_hashfull(slots: impl Iterator<Item = Slot>, sampled: usize, age: u8) -> u16 {
+    let used = slots
+        .filter_map(|slot| TableEntry::unpack(slot.data))
+        .filter(|entry| entry.age == age)
+        .count();
+
+    (used * 1000 / sampled) as u16
+}
+
+#[derive(Copy, Clone, Default)]
+#[repr(align(64))]
+struct Bucket {
+    slots: [Slot; BUCKET_SIZE],
+}
+
+/// Write a table: header (magic, version, key bits, bucket size, key set fingerprint, age, bucket count) then every slot, all little endian
+fn write_table(writer: &mut impl Write, fingerprint: u64, age: u8, bucket_count: usize, slots: impl Iterator<Item = Slot>) -> Result<(), TableFileError> {
+    writer.write_all(&FILE_MAGIC)?;
+    writer.write_all(&FILE_VERSION.to_le_bytes())?;
+    writer.write_all(&[(std::mem::size_of::<ZobristKey>() * 8) as u8, BUCKET_SIZE as u8])?;
+    writer.write_all(&fingerprint.to_le_bytes())?;
+    writer.write_all(&[age])?;
+    writer.write_all(&(bucket_count as u64).to_le_bytes())?;
+    for slot in slots {
+        writer.write_all(&slot.key.to_le_bytes())?;
+        writer.write_all(&slot.data.to_le_bytes())?;
+    }
+    writer.flush()?;
+
+    Ok(())
+}
+
+fn read_u64(reader: &mut impl Read) -> Result<u64, TableFileError> {
+    let mut bytes = [0u8; 8];
+    reader.read_exact(&mut bytes)?;
+
+    Ok(u64::from_le_bytes(bytes))
+}
+
+/// Read a table written by `write_table`, checking it was made with the key set of `fingerprint`
+fn read_table(reader: &mut impl Read, fingerprint: u64) -> Result<(u8, Vec<Slot>), TableFileError> {
+    let mut magic = [0u8; 4];
+    reader.read_exact(&mut magic)?;
+    if magic != FILE_MAGIC {
+        return Err(TableFileError::BadMagic);
+    }
+    let mut version = [0u8; 2];
+    reader.read_exact(&mut version)?;
+    let version = u16::from_le_bytes(version);
+    if version != FILE_VERSION {
+        return Err(TableFileError::UnsupportedVersion(version));
+    }
+    let mut layout = [0u8; 2];
+    reader.read_exact(&mut layout)?;
+    if layout != [(std::mem::size_of::<ZobristKey>() * 8) as u8, BUCKET_SIZE as u8] {
+        return Err(TableFileError::InvalidLayout);
+    }
+    if read_u64(reader)? != fingerprint {
+        return Err(TableFileError::KeySetMismatch);
+    }
+    let mut age = [0u8; 1];
+    reader.read_exact(&mut age)?;
+    let bucket_count = read_u64(reader)?;
+    if age[0] >= AGE_CYCLE || bucket_count == 0 || bucket_count > (usize::MAX / BUCKET_BYTES) as u64 {
+        return Err(TableFileError::InvalidLayout);
+    }
+
+    let mut slots = Vec::new();
+    for _ in 0..bucket_count as usize * BUCKET_SIZE {
+        let key = read_u64(reader)?;
+        let data = read_u64(reader)?;
+        slots.push(Slot { key, data });
+    }
+
+    Ok((age[0], slots))
+}
+
+/// Write a file through a temporary sibling that is synced then renamed over it, so the old file survives a failed save
+fn save_atomically(path: &Path, save: impl FnOnce(&mut BufWriter<File>) -> Result<(), TableFileError>) -> Result<(), TableFileError> {
+    let mut temp_name = path.file_name().ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "path has no file name"))?.to_os_string();
+    temp_name.push(format!(".{}.tmp", std::process::id()));
+    let temp_path = path.with_file_name(temp_name);
+
+    let result = write_and_rename(path, &temp_path, save);
+    if result.is_err() {
+        let _ = fs::remove_file(&temp_path);
+    }
+
+    result
+}
+
+fn write_and_rename(path: &Path, temp_path: &Path, save: impl FnOnce(&mut BufWriter<File>) -> Result<(), TableFileError>) -> Result<(), TableFileError> {
+    let mut writer = BufWriter::new(File::create(temp_path)?);
+    save(&mut writer)?;
+    writer.into_inner().map_err(|error| error.into_error())?.sync_all()?;
+    fs::rename(temp_path, path)?;
+
+    Ok(())
+}
+
+/// Fixed size cache of search results keyed by Zobrist hash
+#[derive(Clone)]
+pub struct TranspositionTable {
+    buckets: Vec<Bucket>,
+    age: u8,
+}
+
+impl TranspositionTable {
+    /// Allocate a table taking roughly `mb` megabytes
+    pub fn new(mb: usize) -> Self {
+        TranspositionTable {
+            buckets: vec![Bucket::default(); bucket_count(mb)],
+            age: 0,
+        }
+    }
+    /// Reallocate to roughly `mb` megabytes, dropping every entry
+    pub fn resize(&mut self, mb: usize) {
+        self.buckets = vec![Bucket::default(); bucket_count(mb)];
+        self.age = 0;
+    }
+    /// Drop every entry
+    pub fn clear(&mut self) {
+        for bucket in self.buckets.iter_mut() {
+            *bucket = Bucket::default();
+        }
+        self.age = 0;
+    }
+    /// Number of entries the table holds
+    #[inline]
+    pub fn capacity(&self) -> usize {
+        self.buckets.len() * BUCKET_SIZE
+    }
+    /// Current search generation
+    #[inline]
+    pub fn age(&self) -> u8 {
+        self.age
+    }
+    /// Start a new search generation so older entries get replaced first
+    pub fn new_search(&mut self) {
+        self.age = (self.age + 1) % AGE_CYCLE;
+    }
+    /// Look up a position, adjusting mate scores for the ply it is probed at
+    pub fn probe(&self, key: ZobristKey, ply: u8) -> Option<TableEntry> {
+        let (index_key, check_key) = split_key(key);
+        let bucket = &self.buckets[bucket_index(index_key, self.buckets.len())];
+        find_entry(&bucket.slots, check_key, ply)
+    }
+    /// Store a search result for a position searched at a ply
+    #[allow(clippy::too_many_arguments)]
+    pub fn store(&mut self, key: ZobristKey, ply: u8, depth: u8, bound: Bound, score: i16, eval: i16, best_move: PackedMove) {
+        let (index_key, check_key) = split_key(key);
+        let age = self.age;
+        let bucket_count = self.buckets.len();
+        let bucket = &mut self.buckets[bucket_index(index_key, bucket_count)];
+        let slot = &mut bucket.slots[replacement_slot(&bucket.slots, check_key, age)];
+
+        let entry = TableEntry {
+            depth,
+            bound,
+            score: score_to_table(score, ply),
+            eval,
+            best_move: kept_move(*slot, check_key, best_move),
+            age,
+        };
+        *slot = Slot { key: check_key, data: entry.pack() };
+    }
+    /// Permille of sampled entries written during the current search
+    pub fn hashfull(&self) -> u16 {
+        let sample_buckets = (1000 / BUCKET_SIZE).min(self.buckets.len());
+        let slots = self.buckets[..sample_buckets].iter().flat_map(|bucket| bucket.slots.iter().copied());
+
+        sampled_hashfull(slots, sample_buckets * BUCKET_SIZE, self.age)
+    }
+    /// Save every entry so the table can be reloaded later
+    #[inline]
+    pub fn save(&self, writer: &mut impl Write) -> Result<(), TableFileError> {
+        self.save_with_keys::<DefaultKeys>(writer)
+    }
+    /// Save every entry, marked as hashed with the key set `K`
+    pub fn save_with_keys<K: ZobristKeys + Default>(&self, writer: &mut impl Write) -> Result<(), TableFileError> {
+        let slots = self.buckets.iter().flat_map(|bucket| bucket.slots.iter().copied());
+        write_table(writer, zobrist::key_set_fingerprint::<K>(), self.age, self.buckets.len(), slots)
+    }
+    /// Load a table saved with the default key set, sized as it was saved
+    #[inline]
+    pub fn load(reader: &mut impl Read) -> Result<Self, TableFileError> {
+        TranspositionTable::load_with_keys::<DefaultKeys>(reader)
+    }
+    /// Load a table saved with the key set `K`, sized as it was saved
+    pub fn load_with_keys<K: ZobristKeys + Default>(reader: &mut impl Read) -> Result<Self, TableFileError> {
+        let (age, slots) = read_table(reader, zobrist::key_set_fingerprint::<K>())?;
+        let buckets = slots.chunks(BUCKET_SIZE).map(|chunk| {
+            let mut bucket = Bucket::default();
+            bucket.slots.copy_from_slice(chunk);
+            bucket
+        }).collect();
+
+        Ok(TranspositionTable { buckets, age })
+    }
+    /// Save to a file, atomically replacing it
+    pub fn save_file(&self, path: impl AsRef<Path>) -> Result<(), TableFileError> {
+        save_atomically(path.as_ref(), |writer| self.save(writer))
+    }
+    /// Load from a file
+    pub fn load_file(path: impl AsRef<Path>) -> Result<Self, TableFileError> {
+        TranspositionTable::load(&mut BufReader::new(File::open(path)?))
+    }
+}
+
+impl Default for TranspositionTable {
+    fn default() -> Self {
+        TranspositionTable::new(16)
+    }
+}
+
+/// Slot readable and writable from many threads, the key is stored xored with the data
+///
+/// A reader that sees halves of two different writes recomputes a key that matches neither,
+/// so torn entries are treated as misses instead of being returned for the wrong position.
+#[derive(Default)]
+struct AtomicSlot {
+    key_xor_data: AtomicU64,
+    data: AtomicU64,
+}
+
+impl AtomicSlot {
+    #[inline]
+    fn load(&self) -> Slot {
+        let data = self.data.load(Ordering::Relaxed);
+        let key = self.key_xor_data.load(Ordering::Relaxed) ^ data;
+
+        Slot { key, data }
+    }
+    #[inline]
+    fn store(&self, slot: Slot) {
+        self.data.store(slot.data, Ordering::Relaxed);
+        self.key_xor_data.store(slot.key ^ slot.data, Ordering::Relaxed);
+    }
+}
+
+#[derive(Default)]
+#[repr(align(64))]
+struct AtomicBucket {
+    slots: [AtomicSlot; BUCKET_SIZE],
+}
+
+impl AtomicBucket {
+    #[inline]
+    fn load(&self) -> [Slot; BUCKET_SIZE] {
+        let mut slots = [Slot::default(); BUCKET_SIZE];
+        for (slot, atomic_slot) in slots.iter_mut().zip(self.slots.iter()) {
+            *slot = atomic_slot.load();
+        }
+
+        slots
+    }
+}
+
+/// Transposition table shared between search threads without locking
+pub struct SharedTranspositionTable {
+    buckets: Vec<AtomicBucket>,
+    age: AtomicU8,
+}
+
+impl SharedTranspositionTable {
+    /// Allocate a table taking roughly `mb` megabytes
+    pub fn new(mb: usize) -> Self {
+        SharedTranspositionTable {
+            buckets: (0..bucket_count(mb)).map(|_| AtomicBucket::default()).collect(),
+            age: AtomicU8::new(0),
+        }
+    }
+    /// Reallocate to roughly `mb` megabytes, dropping every entry
+    pub fn resize(&mut self, mb: usize) {
+        *self = SharedTranspositionTable::new(mb);
+    }
+    /// Drop every entry
+    pub fn clear(&self) {
+        for slot in self.buckets.iter().flat_map(|bucket| bucket.slots.iter()) {
+            slot.store(Slot::default());
+        }
+        self.age.store(0, Ordering::Relaxed);
+    }
+    /// Number of entries the table holds
+    #[inline]
+    pub fn capacity(&self) -> usize {
+        self.buckets.len() * BUCKET_SIZE
+    }
+    /// Current search generation
+    #[inline]
+    pub fn age(&self) -> u8 {
+        self.age.load(Ordering::Relaxed)
+    }
+    /// Start a new search generation so older entries get replaced first
+    pub fn new_search(&self) {
+        self.age.store((self.age() + 1) % AGE_CYCLE, Ordering::Relaxed);
+    }
+    /// Look up a position, adjusting mate scores for the ply it is probed at
+    pub fn probe(&self, key: ZobristKey, ply: u8) -> Option<TableEntry> {
+        let (index_key, check_key) = split_key(key);
+        let bucket = &self.buckets[bucket_index(index_key, self.buckets.len())];
+        find_entry(&bucket.load(), check_key, ply)
+    }
+    /// Store a search result for a position searched at a ply
+    #[allow(clippy::too_many_arguments)]
+    pub fn store(&self, key: ZobristKey, ply: u8, depth: u8, bound: Bound, score: i16, eval: i16, best_move: PackedMove) {
+        let (index_key, check_key) = split_key(key);
+        let age = self.age();
+        let bucket = &self.buckets[bucket_index(index_key, self.buckets.len())];
+        let slots = bucket.load();
+        let slot_index = replacement_slot(&slots, check_key, age);
+
+        let entry = TableEntry {
+            depth,
+            bound,
+            score: score_to_table(score, ply),
+            eval,
+            best_move: kept_move(slots[slot_index], check_key, best_move),
+            age,
+        };
+        bucket.slots[slot_index].store(Slot { key: check_key, data: entry.pack() });
+    }
+    /// Permille of sampled entries written during the current search
+    pub fn hashfull(&self) -> u16 {
+        let sample_buckets = (1000 / BUCKET_SIZE).min(self.buckets.len());
+        let slots = self.buckets[..sample_buckets].iter().flat_map(|bucket| bucket.slots.iter().map(AtomicSlot::load));
+
+        sampled_hashfull(slots, sample_buckets * BUCKET_SIZE, self.age())
+    }
+    /// Save every entry so the table can be reloaded later (entries written meanwhile may or may not be included)
+    #[inline]
+    pub fn save(&self, writer: &mut impl Write) -> Result<(), TableFileError> {
+        self.save_with_keys::<DefaultKeys>(writer)
+    }
+    /// Save every entry, marked as hashed with the key set `K`
+    pub fn save_with_keys<K: ZobristKeys + Default>(&self, writer: &mut impl Write) -> Result<(), TableFileError> {
+        let slots = self.buckets.iter().flat_map(|bucket| bucket.slots.iter().map(AtomicSlot::load));
+        write_table(writer, zobrist::key_set_fingerprint::<K>(), self.age(), self.buckets.len(), slots)
+    }
+    /// Load a table saved with the default key set, sized as it was saved
+    #[inline]
+    pub fn load(reader: &mut impl Read) -> Result<Self, TableFileError> {
+        SharedTranspositionTable::load_with_keys::<DefaultKeys>(reader)
+    }
+    /// Load a table saved with the key set `K`, sized as it was saved
+    pub fn load_with_keys<K: ZobristKeys + Default>(reader: &mut impl Read) -> Result<Self, TableFileError> {
+        let (age, slots) = read_table(reader, zobrist::key_set_fingerprint::<K>())?;
+        let buckets = slots.chunks(BUCKET_SIZE).map(|chunk| {
+            let bucket = AtomicBucket::default();
+            for (atomic_slot, &slot) in bucket.slots.iter().zip(chunk.iter()) {
+                atomic_slot.store(slot);
+            }
+            bucket
+        }).collect();
+
+        Ok(SharedTranspositionTable { buckets, age: AtomicU8::new(age) })
+    }
+    /// Save to a file, atomically replacing it
+    pub fn save_file(&self, path: impl AsRef<Path>) -> Result<(), TableFileError> {
+        save_atomically(path.as_ref(), |writer| self.save(writer))
+    }
+    /// Load from a file
+    pub fn load_file(path: impl AsRef<Path>) -> Result<Self, TableFileError> {
+        SharedTranspositionTable::load(&mut BufReader::new(File::open(path)?))
+    }
+}
+
+impl Default for SharedTranspositionTable {
+    fn default() -> Self {
+        SharedTranspositionTable::new(16)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::side::Side;
+    use crate::square::named;
+    use crate::polyglot::PolyglotKeys;
+    use crate::zobrist::ZOBRIST_KEYS;
+
+    #[test]
+    fn packed_move_round_trips() {
+        let m = Move::new(Side::White, Piece::Pawn, named::G7, named::H8).capture(Piece::Rook).promote(Piece::Knight).clone();
+        let packed = PackedMove::from(&m);
+        assert_eq!(packed.from_square(), named::G7);
+        assert_eq!(packed.to_square(), named::H8);
+        assert_eq!(packed.promotion(), Some(Piece::Knight));
+        assert!(packed.matches(&m));
+        assert!(!packed.is_none());
+
+        let quiet = PackedMove::new(named::E2, named::E4, None);
+        assert_eq!(quiet.promotion(), None);
+        assert!(PackedMove::NONE.is_none());
+    }
+
+    #[test]
+    fn entry_packing_round_trips() {
+        let entry = TableEntry {
+            depth: 255,
+            bound: Bound::Lower,
+            score: -31990,
+            eval: -12,
+            best_move: PackedMove::new(named::A1, named::H8, Some(Piece::Queen)),
+            age: 63,
+        };
+        assert_eq!(TableEntry::unpack(entry.pack()), Some(entry));
+        assert_eq!(TableEntry::unpack(0), None);
+    }
+
+    #[test]
+    fn store_and_probe_works() {
+        let mut table = TranspositionTable::new(1);
+        let best_move = PackedMove::new(named::E2, named::E4, None);
+        table.store(0x1234_5678_9ABC_DEF0, 0, 7, Bound::Exact, 35, 20, best_move);
+
+        let entry = table.probe(0x1234_5678_9ABC_DEF0, 0).unwrap();
+        assert_eq!(entry.depth, 7);
+        assert_eq!(entry.bound, Bound::Exact);
+        assert_eq!(entry.score, 35);
+        assert_eq!(entry.eval, 20);
+        assert_eq!(entry.best_move, best_move);
+        assert!(table.probe(0x1234_5678_9ABC_DEF1, 0).is_none());
+
+        // Storing without a move keeps the old one
+        table.store(0x1234_5678_9ABC_DEF0, 0, 8, Bound::Upper, -5, 20, PackedMove::NONE);
+        let entry = table.probe(0x1234_5678_9ABC_DEF0, 0).unwrap();
+        assert_eq!(entry.depth, 8);
+        assert_eq!(entry.best_move, best_move);
+    }
+
+    #[test]
+    fn mate_scores_are_ply_adjusted() {
+        let mut table = TranspositionTable::new(1);
+        // Mate in 3 plies found 5 plies from the root
+        table.store(42, 5, 3, Bound::Exact, MATE_SCORE - 8, 0, PackedMove::NONE);
+        // Reached 2 plies from the root by another path the mate is 5 plies away
+        assert_eq!(table.probe(42, 2).unwrap().score, MATE_SCORE - 5);
+
+        table.store(43, 4, 3, Bound::Exact, -MATE_SCORE + 6, 0, PackedMove::NONE);
+        assert_eq!(table.probe(43, 0).unwrap().score, -MATE_SCORE + 2);
+
+        assert_eq!(score_to_table(150, 10), 150);
+        assert_eq!(score_from_table(-150, 10), -150);
+    }
+
+    #[test]
+    fn replacement_prefers_shallow_and_old_entries() {
+        let mut table = TranspositionTable::new(0);
+        assert_eq!(table.capacity(), BUCKET_SIZE);
+        for key in 1..=4 {
+            table.store(key, 0, key as u8 * 2, Bound::Exact, 0, 0, PackedMove::NONE);
+        }
+        // Full bucket loses its shallowest entry
+        table.store(5, 0, 5, Bound::Exact, 0, 0, PackedMove::NONE);
+        assert!(table.probe(1, 0).is_none());
+        assert!((2..=5).all(|key| table.probe(key, 0).is_some()));
+
+        // Entries from an older search go before deeper current ones
+        table.new_search();
+        table.store(6, 0, 1, Bound::Exact, 0, 0, PackedMove::NONE);
+        table.store(7, 0, 1, Bound::Exact, 0, 0, PackedMove::NONE);
+        assert!(table.probe(2, 0).is_none());
+        assert!(table.probe(5, 0).is_none());
+        assert!(table.probe(6, 0).is_some() && table.probe(7, 0).is_some());
+    }
+
+    #[test]
+    fn resize_clear_and_hashfull_work() {
+        let mut table = TranspositionTable::new(1);
+        assert_eq!(table.capacity(), 1024 * 1024 / 16);
+        assert_eq!(table.hashfull(), 0);
+        // Pairs of distinct Zobrist keys give well spread test keys in either key width
+        let key = |index: usize| ZOBRIST_KEYS[index % 781] ^ ZOBRIST_KEYS[index / 781 + 1];
+        for index in 0..200_000 {
+            table.store(key(index), 0, 1, Bound::Exact, 0, 0, PackedMove::NONE);
+        }
+        assert!(table.hashfull() > 900);
+
+        table.new_search();
+        assert_eq!(table.hashfull(), 0);
+
+        table.clear();
+        assert!(table.probe(key(199_999), 0).is_none());
+
+        table.resize(2);
+        assert_eq!(table.capacity(), 2 * 1024 * 1024 / 16);
+        assert_eq!(table.age(), 0);
+    }
+
+    #[test]
+    fn shared_table_works_like_the_plain_one() {
+        let table = SharedTranspositionTable::new(1);
+        assert_eq!(table.capacity(), TranspositionTable::new(1).capacity());
+        let best_move = PackedMove::new(named::G1, named::F3, None);
+        table.store(99, 3, 6, Bound::Lower, MATE_SCORE - 10, 7, best_move);
+        let entry = table.probe(99, 1).unwrap();
+        assert_eq!(entry.score, MATE_SCORE - 8);
+        assert_eq!(entry.best_move, best_move);
+        assert_eq!(entry.bound, Bound::Lower);
+
+        table.store(99, 0, 7, Bound::Exact, 0, 7, PackedMove::NONE);
+        assert_eq!(table.probe(99, 0).unwrap().best_move, best_move);
+
+        table.new_search();
+        assert_eq!(table.age(), 1);
+        table.clear();
+        assert!(table.probe(99, 0).is_none());
+        assert_eq!(table.hashfull(), 0);
+    }
+
+    #[test]
+    fn shared_table_is_sync() {
+        fn assert_sync<T: Send + Sync>() {}
+        assert_sync::<SharedTranspositionTable>();
+    }
+
+    #[test]
+    fn shared_table_survives_concurrent_writes() {
+        use crate::position::Position;
+        use crate::zobrist::ZobristHashable;
+        use std::str::FromStr;
+
+        let fens = [
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+            "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 b kq - 0 1",
+        ];
+        let positions: Vec<Position> = fens.iter().map(|fen| Position::from_str(fen).unwrap()).collect();
+        // Every pseudo legal move of each position, found by trying all packed moves
+        let promotions = [None, Some(Piece::Knight), Some(Piece::Bishop), Some(Piece::Rook), Some(Piece::Queen)];
+        let moves: Vec<Vec<PackedMove>> = positions.iter().map(|position| {
+            let mut position_moves = Vec::new();
+            for from in 0..64 {
+                for to in 0..64 {
+                    for &promotion in promotions.iter() {
+                        let packed = PackedMove::new(Square(from), Square(to), promotion);
+                        if position.is_pseudo_legal(packed) {
+                            position_moves.push(packed);
+                        }
+                    }
+                }
+            }
+            position_moves
+        }).collect();
+
+        // A single bucket so every thread fights over the same slots
+        let table = SharedTranspositionTable::new(0);
+        let hits = AtomicU64::new(0);
+        std::thread::scope(|scope| {
+            for thread in 0..8u64 {
+                let (table, positions, moves, hits) = (&table, &positions, &moves, &hits);
+                scope.spawn(move || {
+                    let mut state = 0x9E37_79B9_7F4A_7C15u64 ^ (thread + 1);
+                    let mut random = move || {
+                        state ^= state << 13;
+                        state ^= state >> 7;
+                        state ^= state << 17;
+                        state
+                    };
+                    for _ in 0..20_000 {
+                        let index = random() as usize % positions.len();
+                        let position_moves = &moves[index];
+                        let best_move = position_moves[random() as usize % position_moves.len()];
+                        table.store(positions[index].zobrist_hash(), 0, (random() % 64) as u8, Bound::Exact, (random() % 200) as i16, 0, best_move);
+
+                        let probed = random() as usize % positions.len();
+                        if let Some(entry) = table.probe(positions[probed].zobrist_hash(), 0) {
+                            assert!(positions[probed].is_pseudo_legal(entry.best_move), "Corrupted move {:?} returned for {}", entry.best_move, fens[probed]);
+                            hits.fetch_add(1, Ordering::Relaxed);
+                        }
+                    }
+                });
+            }
+        });
+        assert!(hits.load(Ordering::Relaxed) > 0);
+    }
+
+    #[test]
+    fn save_and_load_round_trips() {
+        let mut table = TranspositionTable::new(1);
+        table.new_search();
+        let best_move = PackedMove::new(named::D2, named::D4, None);
+        table.store(1234, 0, 9, Bound::Exact, 17, 3, best_move);
+        table.store(5678, 2, 4, Bound::Upper, -MATE_SCORE + 9, -40, PackedMove::NONE);
+
+        let mut bytes = Vec::new();
+        table.save(&mut bytes).unwrap();
+        assert_eq!(bytes.len(), 25 + table.capacity() * 16);
+        let loaded = TranspositionTable::load(&mut bytes.as_slice()).unwrap();
+        assert_eq!(loaded.capacity(), table.capacity());
+        assert_eq!(loaded.age(), 1);
+        assert_eq!(loaded.probe(1234, 0), table.probe(1234, 0));
+        assert_eq!(loaded.probe(5678, 2), table.probe(5678, 2));
+        assert_eq!(loaded.hashfull(), table.hashfull());
+
+        // Both table kinds share the format
+        let shared = SharedTranspositionTable::load(&mut bytes.as_slice()).unwrap();
+        assert_eq!(shared.probe(1234, 0).unwrap().best_move, best_move);
+        let mut shared_bytes = Vec::new();
+        shared.save(&mut shared_bytes).unwrap();
+        assert_eq!(shared_bytes, bytes);
+    }
+
+    #[test]
+    fn load_rejects_bad_files() {
+        let mut bytes = Vec::new();
+        TranspositionTable::new(0).save(&mut bytes).unwrap();
+
+        let mut bad_magic = bytes.clone();
+        bad_magic[0] = b'X';
+        assert!(matches!(TranspositionTable::load(&mut bad_magic.as_slice()), Err(TableFileError::BadMagic)));
+
+        let mut bad_version = bytes.clone();
+        bad_version[4] = 99;
+        assert!(matches!(TranspositionTable::load(&mut bad_version.as_slice()), Err(TableFileError::UnsupportedVersion(99))));
+
+        let mut other_keys = bytes.clone();
+        other_keys[8] ^= 1;
+        assert!(matches!(TranspositionTable::load(&mut other_keys.as_slice()), Err(TableFileError::KeySetMismatch)));
+
+        // A table hashed with another key set is rejected unless loaded with that set
+        let mut polyglot = Vec::new();
+        TranspositionTable::new(0).save_with_keys::<PolyglotKeys>(&mut polyglot).unwrap();
+        assert!(matches!(TranspositionTable::load(&mut polyglot.as_slice()), Err(TableFileError::KeySetMismatch)));
+        assert!(matches!(SharedTranspositionTable::load(&mut polyglot.as_slice()), Err(TableFileError::KeySetMismatch)));
+        assert!(TranspositionTable::load_with_keys::<PolyglotKeys>(&mut polyglot.as_slice()).is_ok());
+        assert!(matches!(TranspositionTable::load_with_keys::<PolyglotKeys>(&mut bytes.as_slice()), Err(TableFileError::KeySetMismatch)));
+
+        let truncated = &bytes[..bytes.len() - 1];
+        assert!(matches!(TranspositionTable::load(&mut &truncated[..]), Err(TableFileError::Io(_))));
+    }
+
+    #[test]
+    fn save_and_load_files() {
+        let path = std::env::temp_dir().join(format!("oxide-tt-{}.bin", std::process::id()));
+        let mut table = TranspositionTable::new(1);
+        table.store(42, 0, 12, Bound::Lower, 250, 100, PackedMove::new(named::E7, named::E5, None));
+        TranspositionTable::new(0).save_file(&path).unwrap();
+        // Saving replaces the old file and leaves no temporary file behind
+        table.save_file(&path).unwrap();
+        let loaded = TranspositionTable::load_file(&path);
+        let leftovers = std::fs::read_dir(std::env::temp_dir()).unwrap()
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.file_name().to_string_lossy().starts_with(&*path.file_name().unwrap().to_string_lossy()))
+            .count();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(loaded.unwrap().probe(42, 0), table.probe(42, 0));
+        assert_eq!(leftovers, 1);
+
+        assert!(matches!(TranspositionTable::load_file(&path), Err(TableFileError::Io(_))));
+    }
+}
@@ -1,59 +1,402 @@
-
 use super::position;
 use super::square::Square;
 use super::castles::CastlePermissions;
 use super::side::Side;
-use crate::pieces::ColoredPiece;
-use lazy_static::*;
+use crate::pieces::{ColoredPiece, Piece};
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::ops::{BitXor, BitXorAssign};
+
+/// Key type positions are hashed with, 128 bits wide with the `key128` feature
+#[cfg(not(feature = "key128"))]
+pub type ZobristKey = u64;
+/// Key type positions are hashed with, 128 bits wide with the `key128` feature
+#[cfg(feature = "key128")]
+pub type ZobristKey = u128;
+
+/// Seed the default key set is generated from
+pub const ZOBRIST_SEED: u64 = 0xDEADB33F;
+
+/// Number of keys in a full key table: 768 piece keys, side to move, 4 castle rights and 8 en passant files
+pub const ZOBRIST_KEY_COUNT: usize = 781;
+
+/// Default key table, generated at compile time
+pub static ZOBRIST_KEYS: [ZobristKey; ZOBRIST_KEY_COUNT] = generate_keys(ZOBRIST_SEED);
+
+/// FNV-1a hasher that feeds integers in little endian order, so digests match across platforms
+struct FingerprintHasher(u64);
+
+impl Hasher for FingerprintHasher {
+    fn finish(&self) -> u64 {
+        self.0
+    }
 
-lazy_static! {
-    pub static ref ZOBRIST_KEYS: Vec<u64> = {
-        use rand::{SeedableRng, rngs::StdRng, RngCore};
-        let seed = 0xDEADB33Fu64;
-        let mut rng = StdRng::seed_from_u64(seed);
+    fn write(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.0 ^= byte as u64;
+            self.0 = self.0.wrapping_mul(0x100000001B3);
+        }
+    }
 
-        (0..781).map(|_| rng.next_u64()).collect()
-    };
+    fn write_u64(&mut self, n: u64) {
+        self.write(&n.to_le_bytes());
+    }
+
+    fn write_u128(&mut self, n: u128) {
+        self.write(&n.to_le_bytes());
+    }
 }
 
-pub trait ZobristHashable {
-    fn zobrist_hash(&self) -> u64;
+/// FNV-1a digest of every key in a key set, identifying the set hashes were made with
+pub fn key_set_fingerprint<K: ZobristKeys + Default>() -> u64 {
+    let keys = K::default();
+    let mut hasher = FingerprintHasher(0xCBF29CE484222325);
+    for &side in [Side::White, Side::Black].iter() {
+        for &piece in [Piece::Pawn, Piece::Knight, Piece::Bishop, Piece::Rook, Piece::Queen, Piece::King].iter() {
+            for offset in 0..64 {
+                keys.piece_key(piece.color(side), Square(offset)).hash(&mut hasher);
+            }
+        }
+        keys.side_key(side).hash(&mut hasher);
+    }
+    for bits in 0..16 {
+        keys.castle_key(CastlePermissions::from_bits_truncate(bits)).hash(&mut hasher);
+    }
+    // En passant squares on the third and sixth ranks
+    for offset in (16..24).chain(40..48) {
+        keys.enpassant_key(Square(offset)).hash(&mut hasher);
+    }
+
+    hasher.finish()
 }
 
-impl ZobristHashable for position::Position {
-    fn zobrist_hash(&self) -> u64 {
-        let mut hash = 0u64;
+/// Next state and output of the SplitMix64 generator
+const fn split_mix(state: u64) -> (u64, u64) {
+    let state = state.wrapping_add(0x9E3779B97F4A7C15);
+    let mut z = state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+
+    (state, z ^ (z >> 31))
+}
+
+/// Next state and a key built from one generator output
+#[cfg(not(feature = "key128"))]
+const fn next_key(state: u64) -> (u64, ZobristKey) {
+    split_mix(state)
+}
+
+/// Next state and a key built from two generator outputs
+#[cfg(feature = "key128")]
+const fn next_key(state: u64) -> (u64, ZobristKey) {
+    let (state, high) = split_mix(state);
+    let (state, low) = split_mix(state);
+
+    (state, (high as u128) << 64 | low as u128)
+}
+
+/// Fill a key table from a seed
+pub const fn generate_keys(seed: u64) -> [ZobristKey; ZOBRIST_KEY_COUNT] {
+    let mut keys = [0; ZOBRIST_KEY_COUNT];
+    let mut state = seed;
+    let mut index = 0;
+    while index < ZOBRIST_KEY_COUNT {
+        let (next_state, key) = next_key(state);
+        state = next_state;
+        keys[index] = key;
+        index += 1;
+    }
+
+    keys
+}
+
+/// Source of the keys a position is hashed with
+pub trait ZobristKeys {
+    /// Integer type of the keys
+    type Key: Copy + Default + Eq + Hash + fmt::Debug + fmt::LowerHex + BitXor<Output = Self::Key> + BitXorAssign;
+
+    /// Key for a piece standing on a square
+    fn piece_key(&self, piece: ColoredPiece, square: Square) -> Self::Key;
+    /// Key for a side to move (zero for the side that isn't hashed)
+    fn side_key(&self, side: Side) -> Self::Key;
+    /// Combined key for every castle right held
+    fn castle_key(&self, castle_rights: CastlePermissions) -> Self::Key;
+    /// Key for an en passant square
+    fn enpassant_key(&self, enpassant_square: Square) -> Self::Key;
+
+    /// Hash a position from scratch
+    fn hash(&self, position: &position::Position) -> Self::Key {
+        let mut hash = Self::Key::default();
         // Add each piece to hash
-        for i in 0..64 {
-            let piece = self.squares[i];
-            if piece == ColoredPiece::None {
-                continue;
+        for (offset, &piece) in position.squares.iter().enumerate() {
+            if piece != ColoredPiece::None {
+                hash ^= self.piece_key(piece, Square(offset as u8));
             }
-            hash ^= ZOBRIST_KEYS[12 * i + (piece as usize) - 1];
         }
-        // Hash side to move
-        if self.side == Side::Black {
-            hash ^= ZOBRIST_KEYS[768];
+        hash ^= self.side_key(position.side);
+        hash ^= self.castle_key(position.castle_rights);
+        if let Some(enpassant_square) = position.enpassant_square {
+            hash ^= self.enpassant_key(enpassant_square);
+        }
+
+        hash
+    }
+}
+
+/// Keys from the compile time generated default table, the set positions keep their hash in
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub struct DefaultKeys;
+
+impl ZobristKeys for DefaultKeys {
+    type Key = ZobristKey;
+
+    #[inline]
+    fn piece_key(&self, piece: ColoredPiece, square: Square) -> ZobristKey {
+        debug_assert_ne!(piece, ColoredPiece::None, "Empty squares have no key");
+        ZOBRIST_KEYS[12 * square.offset() as usize + piece as usize]
+    }
+
+    #[inline]
+    fn side_key(&self, side: Side) -> ZobristKey {
+        match side {
+            Side::White => 0,
+            Side::Black => ZOBRIST_KEYS[768],
         }
+    }
 
-        if self.castle_rights.contains(CastlePermissions::BLACK_KING) {
-            hash ^= ZOBRIST_KEYS[769];
+    #[inline]
+    fn castle_key(&self, castle_rights: CastlePermissions) -> ZobristKey {
+        let mut key = 0;
+        if castle_rights.contains(CastlePermissions::BLACK_KING) {
+            key ^= ZOBRIST_KEYS[769];
         }
-        if self.castle_rights.contains(CastlePermissions::BLACK_QUEEN) {
-            hash ^= ZOBRIST_KEYS[770];
+        if castle_rights.contains(CastlePermissions::BLACK_QUEEN) {
+            key ^= ZOBRIST_KEYS[770];
         }
-        if self.castle_rights.contains(CastlePermissions::WHITE_KING) {
-            hash ^= ZOBRIST_KEYS[771];
+        if castle_rights.contains(CastlePermissions::WHITE_KING) {
+            key ^= ZOBRIST_KEYS[771];
         }
-        if self.castle_rights.contains(CastlePermissions::WHITE_QUEEN) {
-            hash ^= ZOBRIST_KEYS[772];
+        if castle_rights.contains(CastlePermissions::WHITE_QUEEN) {
+            key ^= ZOBRIST_KEYS[772];
         }
 
-        if self.enpassant_square.is_some() {
-            let Square(offset) = self.enpassant_square.unwrap();
-            hash ^= ZOBRIST_KEYS[773 + (offset as usize)];
+        key
+    }
+
+    #[inline]
+    fn enpassant_key(&self, enpassant_square: Square) -> ZobristKey {
+        // Only the file matters
+        ZOBRIST_KEYS[773 + enpassant_square.x() as usize]
+    }
+}
+
+/// Keys for parts of a position from the default table, kept up to date alongside the full hash
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Hash)]
+pub struct SubKeys {
+    /// Pawns of both sides
+    pub pawns: ZobristKey,
+    /// Number of each colored piece, independent of where they stand
+    pub material: ZobristKey,
+    /// Every piece other than pawns, white then black
+    pub non_pawns: [ZobristKey; 2],
+}
+
+impl SubKeys {
+    /// Toggle a piece on a square in the pawn or non-pawn keys
+    #[inline]
+    pub(crate) fn toggle_piece(&mut self, piece: ColoredPiece, square: Square) {
+        let key = DefaultKeys.piece_key(piece, square);
+        match piece {
+            ColoredPiece::WPawn | ColoredPiece::BPawn => self.pawns ^= key,
+            ColoredPiece::WBishop | ColoredPiece::WRook | ColoredPiece::WKing | ColoredPiece::WKnight | ColoredPiece::WQueen => self.non_pawns[0] ^= key,
+            _ => self.non_pawns[1] ^= key,
         }
+    }
+    /// Toggle the material key for the piece of a kind at a zero based count
+    #[inline]
+    pub(crate) fn toggle_material(&mut self, piece: ColoredPiece, count: u32) {
+        // Reuses the piece keys with the count standing in for the square
+        self.material ^= DefaultKeys.piece_key(piece, Square(count as u8));
+    }
+}
 
-        hash
+pub trait ZobristHashable {
+    /// Current hash, kept up to date as the position changes
+    fn zobrist_hash(&self) -> ZobristKey;
+    /// Hash computed from scratch
+    fn compute_zobrist_hash(&self) -> ZobristKey;
+    /// Current pawn, material and non-pawn keys
+    fn sub_keys(&self) -> SubKeys;
+    /// Pawn, material and non-pawn keys computed from scratch
+    fn compute_sub_keys(&self) -> SubKeys;
+
+    /// Key of just the pawns
+    #[inline]
+    fn pawn_hash(&self) -> ZobristKey {
+        self.sub_keys().pawns
+    }
+    /// Key of how many of each piece there are
+    #[inline]
+    fn material_hash(&self) -> ZobristKey {
+        self.sub_keys().material
+    }
+    /// Key of a side's pieces other than pawns
+    #[inline]
+    fn non_pawn_hash(&self, side: Side) -> ZobristKey {
+        match side {
+            Side::White => self.sub_keys().non_pawns[0],
+            Side::Black => self.sub_keys().non_pawns[1],
+        }
+    }
+}
+
+impl ZobristHashable for position::Position {
+    #[inline]
+    fn zobrist_hash(&self) -> ZobristKey {
+        self.hash
+    }
+
+    #[inline]
+    fn compute_zobrist_hash(&self) -> ZobristKey {
+        DefaultKeys.hash(self)
+    }
+
+    #[inline]
+    fn sub_keys(&self) -> SubKeys {
+        self.sub_keys
+    }
+
+    fn compute_sub_keys(&self) -> SubKeys {
+        let mut sub_keys = SubKeys::default();
+        let mut counts = [0u32; 12];
+        for (offset, &piece) in self.squares.iter().enumerate() {
+            if piece != ColoredPiece::None {
+                sub_keys.toggle_piece(piece, Square(offset as u8));
+                sub_keys.toggle_material(piece, counts[piece as usize]);
+                counts[piece as usize] += 1;
+            }
+        }
+
+        sub_keys
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::polyglot::PolyglotKeys;
+    use crate::position::Position;
+    use crate::square::named;
+    use std::str::FromStr;
+
+    /// Tiny keys that make hashes easy to work out by hand
+    #[derive(Default)]
+    struct SmallKeys;
+
+    impl ZobristKeys for SmallKeys {
+        type Key = u64;
+
+        fn piece_key(&self, piece: ColoredPiece, square: Square) -> u64 {
+            1 << (piece as u64 + 12 * (square.offset() as u64 & 3))
+        }
+        fn side_key(&self, side: Side) -> u64 {
+            if side == Side::Black { 1 << 48 } else { 0 }
+        }
+        fn castle_key(&self, castle_rights: CastlePermissions) -> u64 {
+            (castle_rights.bits() as u64) << 49
+        }
+        fn enpassant_key(&self, enpassant_square: Square) -> u64 {
+            1 << (53 + enpassant_square.x() as u64)
+        }
+    }
+
+    #[test]
+    fn keys_are_generated_at_compile_time() {
+        const KEYS: [ZobristKey; ZOBRIST_KEY_COUNT] = generate_keys(ZOBRIST_SEED);
+        assert_eq!(KEYS, ZOBRIST_KEYS);
+        assert!(ZOBRIST_KEYS.iter().all(|&key| key != 0));
+        let mut sorted = ZOBRIST_KEYS.to_vec();
+        sorted.sort_unstable();
+        sorted.dedup();
+        assert_eq!(sorted.len(), ZOBRIST_KEY_COUNT, "Generated keys are not unique");
+        assert_ne!(generate_keys(ZOBRIST_SEED + 1)[0], ZOBRIST_KEYS[0]);
+    }
+
+    #[test]
+    fn key_width_follows_feature() {
+        let bits = std::mem::size_of::<ZobristKey>() * 8;
+        assert_eq!(bits, if cfg!(feature = "key128") { 128 } else { 64 });
+        // Keys use the full width
+        assert!(ZOBRIST_KEYS.iter().any(|&key| key >> (bits - 8) != 0));
+    }
+
+    #[test]
+    fn fingerprint_identifies_key_set() {
+        assert_eq!(key_set_fingerprint::<DefaultKeys>(), key_set_fingerprint::<DefaultKeys>());
+        assert_ne!(key_set_fingerprint::<DefaultKeys>(), key_set_fingerprint::<SmallKeys>());
+        assert_ne!(key_set_fingerprint::<DefaultKeys>(), key_set_fingerprint::<PolyglotKeys>());
+        assert_ne!(key_set_fingerprint::<SmallKeys>(), key_set_fingerprint::<PolyglotKeys>());
+    }
+
+    #[test]
+    fn empty_position_hash_is_zero() {
+        let position = Position::default();
+        assert_eq!(position.zobrist_hash(), 0);
+        assert_eq!(position.compute_zobrist_hash(), 0);
+    }
+
+    #[test]
+    fn keys_cover_every_piece_and_square() {
+        let position = Position::from_str("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1").unwrap();
+        assert_eq!(position.zobrist_hash(), position.compute_zobrist_hash());
+        assert_eq!(DefaultKeys.piece_key(ColoredPiece::WPawn, Square(0)), ZOBRIST_KEYS[0]);
+        assert_eq!(DefaultKeys.piece_key(ColoredPiece::BQueen, Square(63)), ZOBRIST_KEYS[767]);
+        assert_eq!(DefaultKeys.enpassant_key(Square(47)), ZOBRIST_KEYS[780]);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn hash_depends_on_state() {
+        let white = Position::from_str("4k3/8/8/8/8/8/8/4K3 w - - 0 1").unwrap();
+        let black = Position::from_str("4k3/8/8/8/8/8/8/4K3 b - - 0 1").unwrap();
+        assert_eq!(white.zobrist_hash() ^ black.zobrist_hash(), DefaultKeys.side_key(Side::Black));
+
+        let castles = Position::from_str("r3k3/8/8/8/8/8/8/4K2R w Kq - 0 1").unwrap();
+        let no_castles = Position::from_str("r3k3/8/8/8/8/8/8/4K2R w - - 0 1").unwrap();
+        assert_eq!(castles.zobrist_hash() ^ no_castles.zobrist_hash(), DefaultKeys.castle_key(CastlePermissions::WHITE_KING_BLACK_QUEEN));
+    }
+
+    #[test]
+    fn sub_keys_split_the_position() {
+        let position = Position::from_str("r3k3/pp6/8/8/8/8/5PPP/4K2R w Kq - 0 1").unwrap();
+        let sub_keys = position.sub_keys();
+        assert_eq!(sub_keys, position.compute_sub_keys());
+        let pieces = DefaultKeys.hash(&position) ^ DefaultKeys.castle_key(position.castle_rights);
+        assert_eq!(sub_keys.pawns ^ sub_keys.non_pawns[0] ^ sub_keys.non_pawns[1], pieces);
+
+        // Pawn and material keys ignore pieces that moved elsewhere
+        let moved = Position::from_str("1r2k3/pp6/8/8/8/8/5PPP/3K3R b - - 0 1").unwrap();
+        assert_eq!(moved.pawn_hash(), position.pawn_hash());
+        assert_eq!(moved.material_hash(), position.material_hash());
+        assert_ne!(moved.non_pawn_hash(Side::White), position.non_pawn_hash(Side::White));
+        assert_ne!(moved.non_pawn_hash(Side::Black), position.non_pawn_hash(Side::Black));
+
+        // Material depends on counts, not which pawn is missing
+        let fewer_a = Position::from_str("r3k3/1p6/8/8/8/8/5PPP/4K2R w - - 0 1").unwrap();
+        let fewer_b = Position::from_str("r3k3/p7/8/8/8/8/5PPP/4K2R w - - 0 1").unwrap();
+        assert_eq!(fewer_a.material_hash(), fewer_b.material_hash());
+        assert_ne!(fewer_a.material_hash(), position.material_hash());
+        assert_ne!(fewer_a.pawn_hash(), fewer_b.pawn_hash());
+    }
+
+    #[test]
+    fn custom_keys_hash_positions() {
+        let position = Position::from_str("4k3/8/8/3pP3/8/8/8/4K3 w K d6 0 1").unwrap();
+        let expected = SmallKeys.piece_key(ColoredPiece::BKing, named::E8)
+            ^ SmallKeys.piece_key(ColoredPiece::BPawn, named::D5)
+            ^ SmallKeys.piece_key(ColoredPiece::WPawn, named::E5)
+            ^ SmallKeys.piece_key(ColoredPiece::WKing, named::E1)
+            ^ (1 << 49)
+            ^ (1 << 56);
+        assert_eq!(SmallKeys.hash(&position), expected);
+    }
+}